@@ -0,0 +1,791 @@
+use crate::compiler::builtins::{call_error, BuiltinRegistry};
+use crate::compiler::compiled_program::CompiledProgram;
+use crate::compiler::memory_layout::{MemoryLayout, Scope};
+use crate::compiler::quadruplets::{
+    convert_quad_op_to_semantic_op, QuadOperator, Quadruplet, QuadrupletList,
+};
+use crate::compiler::semantic_cube::{CuboSemantico, Type};
+use crate::compiler::semantic_tables::{
+    FunctionInfo, FunctionParam, FunctionTable, VarType, VarValue, VariableInfo,
+};
+use crate::compiler::value_table::{type_name, ConstValue, ValueTable};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Comma,
+    Arrow,
+}
+
+/// An instruction waiting for every label and function to be known.
+struct Instruction {
+    line: usize,
+    function: Option<String>,
+    op: QuadOperator,
+    operands: Vec<Token>,
+    target: Option<Token>,
+}
+
+struct Assembler {
+    layout: MemoryLayout,
+    value_table: ValueTable,
+    functions: FunctionTable,
+    function_ids: HashMap<i32, String>,
+    frames: HashMap<String, Vec<Vec<i32>>>,
+    temps: HashMap<String, HashMap<String, i32>>,
+    labels: HashMap<String, i32>,
+    instructions: Vec<Instruction>,
+    builtins: BuiltinRegistry,
+}
+
+/// Builds a program from the text written by `disassembler::disassemble`,
+/// using the default memory layout.
+pub fn assemble(text: &str) -> Result<CompiledProgram, String> {
+    assemble_with_layout(text, MemoryLayout::default())
+}
+
+/// Builds a program from quads written as text. Lines hold a function header
+/// (`function <name> [id <id>] [frame <sizes>]` followed by `param`, `var`,
+/// `const` and `temp` declarations), a label (`<name>:`), an instruction or
+/// `end`, which closes the current function. Variables and temps without an
+/// `@address` get the next free one. Operands are checked against the
+/// segment of their address with the rules of the semantic cube.
+pub fn assemble_with_layout(text: &str, layout: MemoryLayout) -> Result<CompiledProgram, String> {
+    let mut assembler = Assembler {
+        value_table: ValueTable::with_layout(layout.clone()),
+        layout,
+        functions: FunctionTable::new(),
+        function_ids: HashMap::new(),
+        frames: HashMap::new(),
+        temps: HashMap::new(),
+        labels: HashMap::new(),
+        instructions: vec![],
+        builtins: BuiltinRegistry::standard(),
+    };
+
+    let mut function: Option<String> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        assembler
+            .read_line(line, line_number, &mut function)
+            .map_err(|message| format!("line {}: {}", line_number, message))?;
+    }
+    assembler.finish()
+}
+
+impl Assembler {
+    fn read_line(
+        &mut self,
+        line: &str,
+        line_number: usize,
+        function: &mut Option<String>,
+    ) -> Result<(), String> {
+        let tokens = tokenize(line)?;
+        let words: Vec<&str> = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(word) => word.as_str(),
+                _ => "",
+            })
+            .collect();
+        let first = match words.first() {
+            Some(first) => *first,
+            None => return Ok(()),
+        };
+        let position = self.instructions.len() as i32;
+        let inside = || {
+            function
+                .clone()
+                .ok_or_else(|| format!("{} outside of a function", first))
+        };
+
+        match first {
+            "function" => {
+                let name = words.get(1).ok_or("Missing function name")?.to_string();
+                self.declare_function(&name, &words[2..], position)?;
+                *function = Some(name);
+            }
+            "param" | "var" | "const" | "temp" => {
+                let name = inside()?;
+                let field = |index: usize| {
+                    words
+                        .get(index)
+                        .copied()
+                        .filter(|word| !word.is_empty())
+                        .ok_or_else(|| format!("Missing fields in {}", first))
+                };
+                let (var_name, var_type) = (field(1)?, parse_type(field(2)?)?);
+                let address = match words.get(3) {
+                    Some(address) if address.starts_with('@') => Some(parse_address(address)?),
+                    _ => None,
+                };
+                match first {
+                    "param" => self.declare_param(&name, var_name, var_type)?,
+                    "var" => self.declare_var(&name, var_name, var_type, address)?,
+                    "temp" => self.declare_temp(&name, var_name, var_type, address)?,
+                    _ => {
+                        let value = tokens.get(3).ok_or("Missing constant value")?;
+                        self.declare_const(&name, var_name, var_type, value)?
+                    }
+                }
+            }
+            "end" => {
+                inside()?;
+                *function = None;
+            }
+            label if label.ends_with(':') && tokens.len() == 1 => {
+                let label = &label[..label.len() - 1];
+                if self.labels.insert(label.to_string(), position).is_some() {
+                    return Err(format!("Label {} is defined twice", label));
+                }
+            }
+            name => {
+                let op = QuadOperator::from_name(name)
+                    .ok_or_else(|| format!("Unknown instruction {}", name))?;
+                let mut operands = tokens[1..].to_vec();
+                let target = match operands.iter().position(|token| *token == Token::Arrow) {
+                    Some(arrow) => {
+                        let target = operands.get(arrow + 1).cloned();
+                        if target.is_none() || operands.len() != arrow + 2 {
+                            return Err("Expected one operand after ->".to_string());
+                        }
+                        operands.truncate(arrow);
+                        target
+                    }
+                    None => None,
+                };
+                let operands = split_operands(operands)?;
+                self.instructions.push(Instruction {
+                    line: line_number,
+                    function: function.clone(),
+                    op,
+                    operands,
+                    target,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn declare_function(&mut self, name: &str, words: &[&str], start: i32) -> Result<(), String> {
+        if self.functions.contains_key(name) {
+            return Err(format!("Function {} is declared twice", name));
+        }
+        let mut words = words.iter();
+        while let Some(word) = words.next() {
+            match *word {
+                "id" => {
+                    let id = words.next().ok_or("Missing function id")?;
+                    let id = id
+                        .parse()
+                        .map_err(|_| format!("Invalid function id {}", id))?;
+                    if self.function_ids.insert(id, name.to_string()).is_some() {
+                        return Err(format!("Function id {} is used twice", id));
+                    }
+                }
+                "frame" => {
+                    let sizes = words
+                        .by_ref()
+                        .map(|size| size.parse().map_err(|_| format!("Invalid size {}", size)))
+                        .collect::<Result<Vec<i32>, String>>()?;
+                    if sizes.len() != 6 {
+                        return Err("A frame has 6 sizes".to_string());
+                    }
+                    let frame = sizes.chunks(2).map(|pair| pair.to_vec()).collect();
+                    self.frames.insert(name.to_string(), frame);
+                }
+                word => return Err(format!("Unexpected {} in function header", word)),
+            }
+        }
+
+        self.functions.insert(
+            name.to_string(),
+            FunctionInfo {
+                name: name.to_string(),
+                params: vec![],
+                vars: HashMap::new(),
+                vars_amount: vec![vec![0, 0]; 3],
+                start_address: start,
+            },
+        );
+        self.temps.insert(name.to_string(), HashMap::new());
+        Ok(())
+    }
+
+    // Main keeps its variables in the global segments
+    fn var_scope(function: &str) -> Scope {
+        if function == "main" {
+            Scope::Global
+        } else {
+            Scope::Local
+        }
+    }
+
+    fn used_addresses(&self, function: &str) -> Vec<i32> {
+        let vars = self.functions[function]
+            .vars
+            .values()
+            .map(|var| var.address);
+        vars.chain(self.temps[function].values().copied()).collect()
+    }
+
+    // Checks an explicit address, or picks the next free one of the segment
+    fn place(
+        &self,
+        function: &str,
+        var_type: &Type,
+        scope: Scope,
+        address: Option<i32>,
+    ) -> Result<i32, String> {
+        let segment = self.layout.segment(var_type, scope).ok_or_else(|| {
+            format!(
+                "There's no {} segment for {} values",
+                scope,
+                type_name(var_type)
+            )
+        })?;
+        let used = self.used_addresses(function);
+        let address = match address {
+            Some(address) => address,
+            None => used
+                .iter()
+                .filter(|address| segment.contains(**address))
+                .max()
+                .map_or(segment.base, |last| last + 1),
+        };
+        if !segment.contains(address) {
+            return Err(format!(
+                "Address {} is not in the {} {} segment",
+                address,
+                scope,
+                type_name(var_type)
+            ));
+        }
+        if used.contains(&address) {
+            return Err(format!("Address {} is used twice", address));
+        }
+        Ok(address)
+    }
+
+    fn insert_var(&mut self, function: &str, name: &str, var: VariableInfo) -> Result<(), String> {
+        let vars = &mut self.functions.get_mut(function).unwrap().vars;
+        if vars.contains_key(name) || self.temps[function].contains_key(name) {
+            return Err(format!("Variable {} is already declared", name));
+        }
+        vars.insert(name.to_string(), var);
+        Ok(())
+    }
+
+    fn declare_param(&mut self, function: &str, name: &str, var_type: Type) -> Result<(), String> {
+        let param_type = var_type_of(&var_type)?;
+        self.declare_var(function, name, var_type, None)?;
+        self.functions
+            .get_mut(function)
+            .unwrap()
+            .params
+            .push(FunctionParam {
+                var_type: param_type,
+                name: name.to_string(),
+            });
+        Ok(())
+    }
+
+    fn declare_var(
+        &mut self,
+        function: &str,
+        name: &str,
+        var_type: Type,
+        address: Option<i32>,
+    ) -> Result<(), String> {
+        // The variable of a param is declared along with it
+        let function_info = &self.functions[function];
+        if let Some(var) = function_info.vars.get(name) {
+            let is_param = function_info.params.iter().any(|param| param.name == name);
+            let same_type = var_type_of(&var_type)? == var.var_type;
+            if is_param && same_type && address.is_none_or(|address| address == var.address) {
+                return Ok(());
+            }
+        }
+
+        let var_type_info = var_type_of(&var_type)?;
+        let address = self.place(function, &var_type, Self::var_scope(function), address)?;
+        let value = match var_type_info {
+            VarType::Int => VarValue::Int(0),
+            VarType::Float => VarValue::Float(0.0),
+        };
+        self.insert_var(
+            function,
+            name,
+            VariableInfo {
+                name: name.to_string(),
+                value,
+                var_type: var_type_info,
+                address,
+            },
+        )
+    }
+
+    fn declare_const(
+        &mut self,
+        function: &str,
+        name: &str,
+        var_type: Type,
+        value: &Token,
+    ) -> Result<(), String> {
+        let address = self.literal(value)?;
+        let value = match self.value_table.constants.get(address) {
+            Some(ConstValue::Int(value)) if var_type == Type::Int => VarValue::Int(*value),
+            Some(ConstValue::Float(value)) if var_type == Type::Float => VarValue::Float(*value),
+            _ => {
+                return Err(format!(
+                    "Constant {} is not a {}",
+                    name,
+                    type_name(&var_type)
+                ))
+            }
+        };
+        self.insert_var(
+            function,
+            name,
+            VariableInfo {
+                name: name.to_string(),
+                value,
+                var_type: var_type_of(&var_type)?,
+                address,
+            },
+        )
+    }
+
+    fn declare_temp(
+        &mut self,
+        function: &str,
+        name: &str,
+        var_type: Type,
+        address: Option<i32>,
+    ) -> Result<(), String> {
+        if self.functions[function].vars.contains_key(name)
+            || self.temps[function].contains_key(name)
+        {
+            return Err(format!("Variable {} is already declared", name));
+        }
+        let address = self.place(function, &var_type, Scope::Temp, address)?;
+        self.temps
+            .get_mut(function)
+            .unwrap()
+            .insert(name.to_string(), address);
+        Ok(())
+    }
+
+    // Address of a literal operand, added to the constant pool
+    fn literal(&mut self, token: &Token) -> Result<i32, String> {
+        let value = match token {
+            Token::Text(text) => ConstValue::String(text.clone()),
+            Token::Word(word) => {
+                if let Ok(value) = word.parse::<i64>() {
+                    ConstValue::Int(value)
+                } else if let Ok(value) = word.parse::<f64>() {
+                    ConstValue::Float(value)
+                } else {
+                    return Err(format!("Invalid literal {}", word));
+                }
+            }
+            _ => return Err("Expected a literal".to_string()),
+        };
+        self.value_table.constants.insert(value)
+    }
+
+    fn operand(&mut self, function: Option<&str>, token: &Token) -> Result<i32, String> {
+        let word = match token {
+            Token::Word(word) => word,
+            Token::Text(_) => return self.literal(token),
+            _ => return Err("Expected an operand".to_string()),
+        };
+        if word.starts_with('@') {
+            let address = parse_address(word)?;
+            if self.layout.decode(address).is_none() {
+                return Err(format!("Address {} is not in any segment", address));
+            }
+            return Ok(address);
+        }
+        if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            return self.literal(token);
+        }
+
+        let temp = function.and_then(|function| self.temps[function].get(word).copied());
+        let local = function.and_then(|function| self.functions[function].vars.get(word));
+        let global = self
+            .functions
+            .get("main")
+            .and_then(|main| main.vars.get(word));
+        temp.or(local.or(global).map(|var| var.address))
+            .ok_or_else(|| format!("Unknown operand {}", word))
+    }
+
+    fn label(&self, token: &Token) -> Result<i32, String> {
+        match token {
+            Token::Word(word) if word.starts_with('@') => parse_address(word),
+            Token::Word(word) => self
+                .labels
+                .get(word)
+                .copied()
+                .or_else(|| {
+                    self.functions
+                        .get(word)
+                        .map(|function| function.start_address)
+                })
+                .ok_or_else(|| format!("Unknown label {}", word)),
+            _ => Err("Expected a label".to_string()),
+        }
+    }
+
+    fn function_id(&self, token: &Token) -> Result<i32, String> {
+        match token {
+            Token::Word(word) => self
+                .function_ids
+                .iter()
+                .find(|(_, name)| *name == word)
+                .map(|(id, _)| *id)
+                .ok_or_else(|| format!("Unknown function {}", word)),
+            _ => Err("Expected a function name".to_string()),
+        }
+    }
+
+    fn type_of(&self, address: i32) -> Type {
+        self.value_table.get_var_type(address)
+    }
+
+    fn writable(&self, address: i32) -> Result<i32, String> {
+        match self.layout.decode(address) {
+            Some(segment) if segment.scope != Scope::Constant => Ok(address),
+            _ => Err(format!("Address {} can't be written", address)),
+        }
+    }
+
+    fn finish(mut self) -> Result<CompiledProgram, String> {
+        // Functions without an id get the next free one
+        let mut names: Vec<(i32, String)> = self
+            .functions
+            .values()
+            .filter(|function| function.name != "main")
+            .map(|function| (function.start_address, function.name.clone()))
+            .collect();
+        names.sort();
+        for (_, name) in names {
+            if !self.function_ids.values().any(|id_name| *id_name == name) {
+                let id = (0..)
+                    .find(|id| !self.function_ids.contains_key(id))
+                    .unwrap();
+                self.function_ids.insert(id, name);
+            }
+        }
+
+        let instructions = std::mem::take(&mut self.instructions);
+        let mut quadruplets = QuadrupletList::new();
+        let mut callee: Option<String> = None;
+        let mut arguments: Vec<Type> = vec![];
+        for instruction in &instructions {
+            let quad = self
+                .encode(instruction, &mut callee, &mut arguments)
+                .map_err(|message| format!("line {}: {}", instruction.line, message))?;
+            // `@N` targets aren't labels, so they can point anywhere
+            let target = match quad.op() {
+                QuadOperator::Goto => Some(quad.arg1),
+                QuadOperator::GotoF | QuadOperator::GotoV => quad.arg2,
+                _ => None,
+            };
+            if let Some(target) = target {
+                if !(0..=instructions.len() as i32).contains(&target) {
+                    return Err(format!(
+                        "line {}: Jump target {} is outside the program",
+                        instruction.line, target
+                    ));
+                }
+            }
+            quadruplets.push(quad);
+        }
+
+        let names: Vec<String> = self.functions.keys().cloned().collect();
+        for name in names {
+            let frame = match self.frames.get(&name) {
+                Some(frame) => frame.clone(),
+                None => self.frame_of(&name),
+            };
+            self.functions.get_mut(&name).unwrap().vars_amount = frame;
+        }
+        let globals = self
+            .functions
+            .get("main")
+            .map_or(vec![vec![0, 0]; 3], |main| main.vars_amount.clone());
+
+        Ok(CompiledProgram {
+            layout: self.layout,
+            constants: self.value_table.constants,
+            functions: self.functions,
+            function_ids: self.function_ids,
+            globals,
+            quadruplets,
+            builtins: self.builtins,
+        })
+    }
+
+    // Values a function needs, from the highest address it declares in each segment
+    fn frame_of(&self, function: &str) -> Vec<Vec<i32>> {
+        let used = self.used_addresses(function);
+        [Type::Int, Type::Float, Type::Bool]
+            .iter()
+            .map(|var_type| {
+                [Self::var_scope(function), Scope::Temp]
+                    .iter()
+                    .map(|scope| match self.layout.segment(var_type, *scope) {
+                        Some(segment) => used
+                            .iter()
+                            .filter(|address| segment.contains(**address))
+                            .map(|address| address - segment.base + 1)
+                            .max()
+                            .unwrap_or(0),
+                        None => 0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn encode(
+        &mut self,
+        instruction: &Instruction,
+        callee: &mut Option<String>,
+        arguments: &mut Vec<Type>,
+    ) -> Result<Quadruplet, String> {
+        let function = instruction.function.as_deref();
+        let op = instruction.op;
+        let operands = &instruction.operands;
+        let expected = match op {
+            QuadOperator::EndFunc | QuadOperator::EndProgram => 0,
+            QuadOperator::Goto
+            | QuadOperator::Print
+            | QuadOperator::Memory
+            | QuadOperator::GoSub
+            | QuadOperator::Param
+            | QuadOperator::CallBuiltin => 1,
+            _ => 2,
+        };
+        // A `PARAM` without a target passes an argument to a builtin
+        let needs_target = match op {
+            QuadOperator::Param => instruction.target.is_some(),
+            op => op.is_binary() || op == QuadOperator::CallBuiltin,
+        };
+        if operands.len() != expected || instruction.target.is_some() != needs_target {
+            return Err(format!("Wrong operands for {}", op.name()));
+        }
+
+        let quad = match op {
+            QuadOperator::Goto => Quadruplet::new(op.code(), self.label(&operands[0])?, None, None),
+            QuadOperator::GotoF | QuadOperator::GotoV => {
+                let condition = self.operand(function, &operands[0])?;
+                if self.type_of(condition) != Type::Bool {
+                    return Err(format!("The condition of {} must be a bool", op.name()));
+                }
+                Quadruplet::new(op.code(), condition, Some(self.label(&operands[1])?), None)
+            }
+            QuadOperator::Assign => {
+                let destination = self.operand(function, &operands[0])?;
+                let destination = self.writable(destination)?;
+                let source = self.operand(function, &operands[1])?;
+                if self.type_of(destination) != self.type_of(source) {
+                    return Err("Types must be the same to assign values".to_string());
+                }
+                Quadruplet::new(op.code(), destination, Some(source), None)
+            }
+            QuadOperator::Print => {
+                Quadruplet::new(op.code(), self.operand(function, &operands[0])?, None, None)
+            }
+            QuadOperator::Memory | QuadOperator::GoSub => {
+                let id = self.function_id(&operands[0])?;
+                if op == QuadOperator::Memory {
+                    *callee = self.function_ids.get(&id).cloned();
+                }
+                Quadruplet::new(op.code(), id, None, None)
+            }
+            QuadOperator::Param if instruction.target.is_none() => {
+                let argument = self.operand(function, &operands[0])?;
+                arguments.push(self.type_of(argument));
+                Quadruplet::new(op.code(), argument, None, None)
+            }
+            QuadOperator::CallBuiltin => {
+                let key = match &operands[0] {
+                    Token::Word(word) => word,
+                    _ => return Err("Expected a builtin".to_string()),
+                };
+                let id = self
+                    .builtins
+                    .find(key)
+                    .ok_or_else(|| format!("There's no builtin {}", key))?;
+                let result = self.operand(function, instruction.target.as_ref().unwrap())?;
+                let result = self.writable(result)?;
+                let builtin = self.builtins.get(id).unwrap();
+                let arguments = std::mem::take(arguments);
+                if !builtin.accepts(&arguments) {
+                    return Err(call_error(&builtin.name, &arguments));
+                }
+                if self.type_of(result) != builtin.result {
+                    return Err(format!(
+                        "The result of {} is a {} value",
+                        key,
+                        type_name(&builtin.result)
+                    ));
+                }
+                Quadruplet::new(
+                    op.code(),
+                    id,
+                    Some(builtin.params.len() as i32),
+                    Some(result),
+                )
+            }
+            QuadOperator::Param => {
+                let callee = callee
+                    .as_deref()
+                    .ok_or("PARAM must follow a MEMORY instruction")?;
+                let source = self.operand(function, &operands[0])?;
+                let param = match instruction.target.as_ref().unwrap() {
+                    Token::Word(word) if word.starts_with('@') => parse_address(word)?,
+                    Token::Word(word) => self.functions[callee]
+                        .vars
+                        .get(word)
+                        .map(|var| var.address)
+                        .ok_or_else(|| format!("Function {} has no param {}", callee, word))?,
+                    _ => return Err("Expected a param name".to_string()),
+                };
+                if self.type_of(source) != self.type_of(param) {
+                    return Err(format!(
+                        "Argument and parameters are not the same type on function {}",
+                        callee
+                    ));
+                }
+                Quadruplet::new(op.code(), source, Some(param), None)
+            }
+            QuadOperator::EndFunc | QuadOperator::EndProgram => {
+                Quadruplet::new(op.code(), 0, None, None)
+            }
+            _ => {
+                let left = self.operand(function, &operands[0])?;
+                let right = self.operand(function, &operands[1])?;
+                let result = self.operand(function, instruction.target.as_ref().unwrap())?;
+                let result = self.writable(result)?;
+                let expected = CuboSemantico::new().get_type(
+                    self.type_of(left),
+                    convert_quad_op_to_semantic_op(op).unwrap(),
+                    self.type_of(right),
+                );
+                if expected == Type::Error {
+                    return Err(format!(
+                        "{} can't be applied to {} and {}",
+                        op.name(),
+                        type_name(&self.type_of(left)),
+                        type_name(&self.type_of(right))
+                    ));
+                }
+                if self.type_of(result) != expected {
+                    return Err(format!(
+                        "The result of {} is a {} value",
+                        op.name(),
+                        type_name(&expected)
+                    ));
+                }
+                Quadruplet::new(op.code(), left, Some(right), Some(result))
+            }
+        };
+        Ok(quad)
+    }
+}
+
+fn parse_type(name: &str) -> Result<Type, String> {
+    match name {
+        "int" => Ok(Type::Int),
+        "float" => Ok(Type::Float),
+        "bool" => Ok(Type::Bool),
+        _ => Err(format!("Invalid type {}", name)),
+    }
+}
+
+fn var_type_of(var_type: &Type) -> Result<VarType, String> {
+    match var_type {
+        Type::Int => Ok(VarType::Int),
+        Type::Float => Ok(VarType::Float),
+        _ => Err(format!("Variables can't be {}", type_name(var_type))),
+    }
+}
+
+fn parse_address(word: &str) -> Result<i32, String> {
+    word[1..]
+        .parse()
+        .map_err(|_| format!("Invalid address {}", word))
+}
+
+// Operands separated by commas
+fn split_operands(tokens: Vec<Token>) -> Result<Vec<Token>, String> {
+    let mut operands = vec![];
+    let mut expect_operand = true;
+    for token in tokens {
+        match (token, expect_operand) {
+            (Token::Comma, false) => expect_operand = true,
+            (token @ (Token::Word(_) | Token::Text(_)), true) => {
+                operands.push(token);
+                expect_operand = false;
+            }
+            _ => return Err("Operands must be separated by commas".to_string()),
+        }
+    }
+    if expect_operand && !operands.is_empty() {
+        return Err("Missing operand after a comma".to_string());
+    }
+    Ok(operands)
+}
+
+// Splits a line in words, string literals, commas and arrows, dropping the
+// comment that starts with `;`
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => break,
+            ',' => tokens.push(Token::Comma),
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                tokens.push(Token::Arrow);
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(c) => text.push(c),
+                            None => return Err("Unterminated string".to_string()),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, ',' | ';' | '"') {
+                        break;
+                    }
+                    word.push(*next);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,341 @@
+use crate::compiler::semantic_cube::{CuboSemantico, Operator, Type};
+use crate::compiler::value_table::{type_name, Value};
+use crate::utils::random::Random;
+use std::fmt;
+use std::sync::Arc;
+
+/// Computes a builtin from arguments that fit its parameters. Errors are
+/// reported by the virtual machine as runtime errors.
+pub type NativeFunction = Arc<dyn Fn(&[Value]) -> Result<Value, String> + Send + Sync>;
+
+/// A builtin that draws from the random numbers of the virtual machine.
+pub type RandomFunction = fn(&mut Random, &[Value]) -> Result<Value, String>;
+
+#[derive(Clone)]
+pub enum BuiltinFunction {
+    Native(NativeFunction),
+    Random(RandomFunction),
+}
+
+/// One signature of a builtin function. Builtins with the same name and
+/// different parameters are separate entries of the registry.
+#[derive(Clone)]
+pub struct Builtin {
+    pub name: String,
+    pub params: Vec<Type>,
+    pub result: Type,
+    pub function: BuiltinFunction,
+    /// Registered by the application embedding Pandora, with a single
+    /// signature that calls have to match exactly.
+    pub host: bool,
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Builtin")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("result", &self.result)
+            .field("host", &self.host)
+            .finish()
+    }
+}
+
+impl Builtin {
+    /// Name followed by the parameter types, like `pow:float:float`, which
+    /// tells apart the signatures of a name.
+    pub fn key(&self) -> String {
+        let mut key = self.name.clone();
+        for param in &self.params {
+            key.push(':');
+            key.push_str(type_name(param));
+        }
+        key
+    }
+
+    /// Whether arguments of these types can be passed, with ints taking the
+    /// place of floats except on host functions.
+    pub fn accepts(&self, args: &[Type]) -> bool {
+        self.params.len() == args.len()
+            && self.params.iter().zip(args).all(|(param, arg)| {
+                param == arg || (!self.host && *param == Type::Float && *arg == Type::Int)
+            })
+    }
+}
+
+/// Error of a call to `name` with arguments that fit none of its signatures.
+pub fn call_error(name: &str, args: &[Type]) -> String {
+    let args: Vec<&str> = args.iter().map(type_name).collect();
+    format!("{} can't be called with ({})", name, args.join(", "))
+}
+
+/// Functions implemented in Rust that programs call like their own. The id
+/// of a builtin, used by `CALLBUILTIN`, is its index in the registry.
+#[derive(Debug, Clone)]
+pub struct BuiltinRegistry {
+    builtins: Vec<Builtin>,
+}
+
+impl Default for BuiltinRegistry {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl BuiltinRegistry {
+    pub fn new() -> Self {
+        BuiltinRegistry { builtins: vec![] }
+    }
+
+    /// `sqrt`, `abs`, `pow`, `min`, `max`, `floor`, `ceil`, `rand_int` and
+    /// `rand_float`.
+    pub fn standard() -> Self {
+        use Type::{Float, Int};
+
+        let mut registry = BuiltinRegistry::new();
+        registry.register("sqrt", vec![Float], Float, sqrt);
+        registry.register("abs", vec![Int], Int, abs_int);
+        registry.register("abs", vec![Float], Float, abs_float);
+        registry.register("pow", vec![Int, Int], Int, pow_int);
+        registry.register("pow", vec![Float, Float], Float, pow_float);
+        registry.register("min", vec![Int, Int], Int, min_int);
+        registry.register("min", vec![Float, Float], Float, min_float);
+        registry.register("max", vec![Int, Int], Int, max_int);
+        registry.register("max", vec![Float, Float], Float, max_float);
+        registry.register("floor", vec![Float], Int, floor);
+        registry.register("ceil", vec![Float], Int, ceil);
+        registry.register_random("rand_int", vec![Int, Int], Int, rand_int);
+        registry.register_random("rand_float", vec![], Float, rand_float);
+        registry
+    }
+
+    pub fn register(
+        &mut self,
+        name: &str,
+        params: Vec<Type>,
+        result: Type,
+        function: impl Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    ) {
+        self.builtins.push(Builtin {
+            name: name.to_string(),
+            params,
+            result,
+            function: BuiltinFunction::Native(Arc::new(function)),
+            host: false,
+        });
+    }
+
+    fn register_random(
+        &mut self,
+        name: &str,
+        params: Vec<Type>,
+        result: Type,
+        function: RandomFunction,
+    ) {
+        self.builtins.push(Builtin {
+            name: name.to_string(),
+            params,
+            result,
+            function: BuiltinFunction::Random(function),
+            host: false,
+        });
+    }
+
+    /// Adds a function of the application embedding Pandora. Its name can't
+    /// be taken by another builtin, and it only takes and returns the types
+    /// programs have values of.
+    pub fn register_host(
+        &mut self,
+        name: &str,
+        params: Vec<Type>,
+        result: Type,
+        function: impl Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        if self.contains(name) {
+            return Err(format!("{} is already a builtin function", name));
+        }
+        let valid = |var_type: &Type| matches!(var_type, Type::Int | Type::Float | Type::Bool);
+        if !params.iter().all(valid) || !valid(&result) {
+            return Err(format!(
+                "Host function {} can only take and return int, float and bool values",
+                name
+            ));
+        }
+        self.register(name, params, result, function);
+        self.builtins.last_mut().unwrap().host = true;
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.find_name(name).is_some()
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Builtin> {
+        usize::try_from(id)
+            .ok()
+            .and_then(|id| self.builtins.get(id))
+    }
+
+    /// Id of the first signature of `name`.
+    pub fn find_name(&self, name: &str) -> Option<i32> {
+        self.builtins
+            .iter()
+            .position(|builtin| builtin.name == name)
+            .map(|id| id as i32)
+    }
+
+    /// Id of the builtin with a `Builtin::key`.
+    pub fn find(&self, key: &str) -> Option<i32> {
+        self.builtins
+            .iter()
+            .position(|builtin| builtin.key() == key)
+            .map(|id| id as i32)
+    }
+
+    /// Id of the signature of `name` the argument types fit, preferring one
+    /// where every type matches. An argument also fits a parameter when the
+    /// cube keeps the parameter's type after adding them, so ints fit float
+    /// parameters but floats don't fit int ones.
+    ///
+    /// Host functions are checked like the program's own functions instead.
+    pub fn resolve(&self, name: &str, args: &[Type], cubo: &CuboSemantico) -> Result<i32, String> {
+        if let Some(id) = self
+            .find_name(name)
+            .filter(|id| self.get(*id).unwrap().host)
+        {
+            let host = self.get(id).unwrap();
+            if host.params.len() != args.len() {
+                return Err(format!(
+                    "Argument quantities doesn't match with function {} declaration",
+                    name
+                ));
+            }
+            if host.params != args {
+                return Err(format!(
+                    "Argument and parameters are not the same type on function {}",
+                    name
+                ));
+            }
+            return Ok(id);
+        }
+        let candidates = || {
+            self.builtins
+                .iter()
+                .enumerate()
+                .filter(|(_, builtin)| builtin.name == name && builtin.params.len() == args.len())
+        };
+        let exact = candidates().find(|(_, builtin)| builtin.params == args);
+        let widened = || {
+            candidates().find(|(_, builtin)| {
+                builtin.params.iter().zip(args).all(|(param, arg)| {
+                    param == arg
+                        || cubo.get_type(param.clone(), Operator::Add, arg.clone()) == *param
+                })
+            })
+        };
+        match exact.or_else(widened) {
+            Some((id, _)) => Ok(id as i32),
+            None => Err(call_error(name, args)),
+        }
+    }
+}
+
+fn int(value: &Value) -> i64 {
+    match value {
+        Value::Int(value) => *value,
+        value => panic!("Expected an int argument, found {:?}", value),
+    }
+}
+
+// Int arguments of float parameters arrive as ints
+fn float(value: &Value) -> f64 {
+    match value {
+        Value::Int(value) => *value as f64,
+        Value::Float(value) => *value,
+        value => panic!("Expected a float argument, found {:?}", value),
+    }
+}
+
+fn sqrt(args: &[Value]) -> Result<Value, String> {
+    match float(&args[0]) {
+        value if value < 0.0 => Err(format!("sqrt of a negative number: {:?}", value)),
+        value => Ok(Value::Float(value.sqrt())),
+    }
+}
+
+fn abs_int(args: &[Value]) -> Result<Value, String> {
+    int(&args[0])
+        .checked_abs()
+        .map(Value::Int)
+        .ok_or_else(|| "Integer overflow".to_string())
+}
+
+fn abs_float(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(float(&args[0]).abs()))
+}
+
+fn pow_int(args: &[Value]) -> Result<Value, String> {
+    let (base, exponent) = (int(&args[0]), int(&args[1]));
+    let exponent = u32::try_from(exponent)
+        .map_err(|_| format!("pow of an int to a negative exponent: {}", exponent))?;
+    base.checked_pow(exponent)
+        .map(Value::Int)
+        .ok_or_else(|| "Integer overflow".to_string())
+}
+
+fn pow_float(args: &[Value]) -> Result<Value, String> {
+    let (base, exponent) = (float(&args[0]), float(&args[1]));
+    match base.powf(exponent) {
+        value if value.is_nan() => Err(format!(
+            "pow of {:?} to {:?} is not a real number",
+            base, exponent
+        )),
+        value => Ok(Value::Float(value)),
+    }
+}
+
+fn min_int(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Int(int(&args[0]).min(int(&args[1]))))
+}
+
+fn min_float(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(float(&args[0]).min(float(&args[1]))))
+}
+
+fn max_int(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Int(int(&args[0]).max(int(&args[1]))))
+}
+
+fn max_float(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(float(&args[0]).max(float(&args[1]))))
+}
+
+fn to_int(name: &str, value: f64, rounded: f64) -> Result<Value, String> {
+    if rounded.is_finite() && rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+        Ok(Value::Int(rounded as i64))
+    } else {
+        Err(format!("{} of {:?} doesn't fit in an int", name, value))
+    }
+}
+
+fn floor(args: &[Value]) -> Result<Value, String> {
+    let value = float(&args[0]);
+    to_int("floor", value, value.floor())
+}
+
+fn ceil(args: &[Value]) -> Result<Value, String> {
+    let value = float(&args[0]);
+    to_int("ceil", value, value.ceil())
+}
+
+fn rand_int(random: &mut Random, args: &[Value]) -> Result<Value, String> {
+    let (low, high) = (int(&args[0]), int(&args[1]));
+    if low > high {
+        return Err(format!("rand_int of an empty range: {} to {}", low, high));
+    }
+    Ok(Value::Int(random.next_int(low, high)))
+}
+
+fn rand_float(random: &mut Random, _: &[Value]) -> Result<Value, String> {
+    Ok(Value::Float(random.next_float()))
+}
@@ -0,0 +1,857 @@
+use crate::compiler::builtins::BuiltinRegistry;
+use crate::compiler::memory_layout::{MemoryLayout, Scope, Segment};
+use crate::compiler::program_manager::ProgramManager;
+use crate::compiler::quadruplets::{QuadOperator, Quadruplet, QuadrupletList};
+use crate::compiler::semantic_cube::Type;
+use crate::compiler::semantic_tables::{
+    FunctionInfo, FunctionParam, FunctionTable, VarType, VarValue, VariableInfo,
+};
+use crate::compiler::value_table::{type_name, ConstValue, ConstantPool};
+use std::collections::{BTreeMap, HashMap};
+
+/// Version written in every object file. Files from another version are
+/// rejected when loading.
+pub const FORMAT_VERSION: u32 = 2;
+
+const TEXT_HEADER: &str = "pandora-object";
+const BINARY_MAGIC: &[u8; 4] = b"PDRA";
+
+const TYPES: [Type; 4] = [Type::Int, Type::Float, Type::Bool, Type::String];
+const SCOPES: [Scope; 4] = [Scope::Global, Scope::Local, Scope::Temp, Scope::Constant];
+
+/// Everything the virtual machine needs to run a compiled program.
+#[derive(Debug, Clone)]
+pub struct CompiledProgram {
+    pub layout: MemoryLayout,
+    pub constants: ConstantPool,
+    pub functions: FunctionTable,
+    pub function_ids: HashMap<i32, String>,
+    pub globals: Vec<Vec<i32>>,
+    pub quadruplets: QuadrupletList,
+    pub builtins: BuiltinRegistry,
+}
+
+impl CompiledProgram {
+    pub fn from_program(program_manager: &ProgramManager) -> Self {
+        let globals = program_manager
+            .tabla_funciones
+            .get("main")
+            .map(|main| main.vars_amount.clone())
+            .unwrap_or_else(|| program_manager.value_table.memory_sizes(Scope::Global));
+
+        CompiledProgram {
+            layout: program_manager.value_table.layout.clone(),
+            constants: program_manager.value_table.constants.clone(),
+            functions: program_manager.tabla_funciones.clone(),
+            function_ids: program_manager.function_ids.clone(),
+            globals,
+            quadruplets: program_manager.quadruplets.clone(),
+            builtins: program_manager.builtins.clone(),
+        }
+    }
+
+    /// Line of the source each quad comes from, for the quads that keep the
+    /// position of their statement.
+    pub fn source_lines(&self, source: &str) -> Vec<Option<usize>> {
+        self.quadruplets
+            .iter()
+            .map(|quad| {
+                quad.position
+                    .map(|position| source[..position].matches('\n').count() + 1)
+            })
+            .collect()
+    }
+
+    /// Reads an object file in either format, calling the standard builtins.
+    pub fn load(data: &[u8]) -> Result<Self, String> {
+        CompiledProgram::load_with_builtins(data, &BuiltinRegistry::standard())
+    }
+
+    /// Reads an object file whose builtins, host functions included, are
+    /// looked up by their key in `builtins`.
+    pub fn load_with_builtins(data: &[u8], builtins: &BuiltinRegistry) -> Result<Self, String> {
+        if data.starts_with(BINARY_MAGIC) {
+            CompiledProgram::from_bytes(data, builtins)
+        } else {
+            let text = std::str::from_utf8(data).map_err(|_| "Invalid object file".to_string())?;
+            CompiledProgram::from_text(text, builtins)
+        }
+    }
+
+    // Key of every builtin the quads call, by the id they call it with
+    fn called_builtins(&self) -> Vec<(i32, String)> {
+        let mut called: Vec<(i32, String)> = self
+            .quadruplets
+            .iter()
+            .filter(|quad| quad.op() == QuadOperator::CallBuiltin)
+            .filter_map(|quad| Some((quad.arg1, self.builtins.get(quad.arg1)?.key())))
+            .collect();
+        called.sort();
+        called.dedup();
+        called
+    }
+
+    // Ids in a file are the ones of the registry that wrote it, so calls are
+    // moved to the id the same key has in `builtins`
+    fn resolve_builtins(
+        &mut self,
+        keys: BTreeMap<i32, String>,
+        builtins: &BuiltinRegistry,
+    ) -> Result<(), String> {
+        let mut ids = HashMap::new();
+        for (id, key) in keys {
+            let resolved = builtins
+                .find(&key)
+                .ok_or_else(|| format!("There's no builtin {}", key))?;
+            ids.insert(id, resolved);
+        }
+        for i in 0..self.quadruplets.len() {
+            let mut quad = self.quadruplets.get(i).unwrap().clone();
+            if quad.op() == QuadOperator::CallBuiltin {
+                quad.arg1 = *ids
+                    .get(&quad.arg1)
+                    .ok_or_else(|| format!("Quad {} calls an unknown builtin", i))?;
+                self.quadruplets.set(i, quad);
+            }
+        }
+        self.builtins = builtins.clone();
+        Ok(())
+    }
+
+    // A file can be corrupt or edited by hand, so everything the machine
+    // indexes with is checked before running it
+    fn validate(&self) -> Result<(), String> {
+        self.check_sizes(&self.globals, Scope::Global)
+            .map_err(|message| format!("Globals: {}", message))?;
+        let end = self.quadruplets.len();
+        for function in self.functions.values() {
+            if function.name != "main" {
+                self.check_sizes(&function.vars_amount, Scope::Local)
+                    .map_err(|message| format!("Function {}: {}", function.name, message))?;
+            }
+            if !(0..=end).contains(&function.start_address) {
+                return Err(format!(
+                    "Function {} starts at {}, outside the program",
+                    function.name, function.start_address
+                ));
+            }
+            let frame = (function.name != "main").then_some(&function.vars_amount);
+            for var in function.vars.values() {
+                self.check_operand(var.address, frame)
+                    .map_err(|message| format!("Variable {}: {}", var.name, message))?;
+            }
+        }
+
+        // Locals and temps of a function live in its frame until its ENDFUNC
+        let mut frame: Option<&Vec<Vec<i32>>> = None;
+        let mut callee: Option<&FunctionInfo> = None;
+        for (index, quad) in self.quadruplets.iter().enumerate() {
+            let index = index as i32;
+            if let Some(function) = self
+                .functions
+                .values()
+                .find(|function| function.name != "main" && function.start_address == index)
+            {
+                frame = Some(&function.vars_amount);
+            }
+            self.check_quad(quad, frame, &mut callee)
+                .map_err(|message| format!("Quad {}: {}", index, message))?;
+            if quad.op() == QuadOperator::EndFunc {
+                frame = None;
+            }
+        }
+        Ok(())
+    }
+
+    // Every size has to fit in the segment of its type and lifetime
+    fn check_sizes(&self, sizes: &[Vec<i32>], scope: Scope) -> Result<(), String> {
+        if sizes.len() > 3 || sizes.iter().any(|type_sizes| type_sizes.len() > 2) {
+            return Err("too many sizes".to_string());
+        }
+        for (var_type, type_sizes) in [Type::Int, Type::Float, Type::Bool].iter().zip(sizes) {
+            for (size, scope) in type_sizes.iter().zip([scope, Scope::Temp]) {
+                let capacity = self
+                    .layout
+                    .segment(var_type, scope)
+                    .map_or(0, |segment| segment.capacity);
+                if !(0..=capacity).contains(size) {
+                    return Err(format!(
+                        "{} {} {} values don't fit in a segment of {}",
+                        size,
+                        scope,
+                        type_name(var_type),
+                        capacity
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_quad<'p>(
+        &'p self,
+        quad: &Quadruplet,
+        frame: Option<&Vec<Vec<i32>>>,
+        callee: &mut Option<&'p FunctionInfo>,
+    ) -> Result<(), String> {
+        let end = self.quadruplets.len();
+        let target = |target: i32| match (0..=end).contains(&target) {
+            true => Ok(()),
+            false => Err(format!("jumps to {}, outside the program", target)),
+        };
+        let arg2 = || quad.arg2.ok_or("missing its second operand".to_string());
+        let result = || quad.result.ok_or("missing its result".to_string());
+
+        match quad.op() {
+            QuadOperator::Goto => target(quad.arg1),
+            QuadOperator::GotoF | QuadOperator::GotoV => {
+                self.check_operand(quad.arg1, frame)?;
+                target(arg2()?)
+            }
+            QuadOperator::Memory | QuadOperator::GoSub => {
+                let function = self
+                    .function_ids
+                    .get(&quad.arg1)
+                    .and_then(|name| self.functions.get(name))
+                    .ok_or_else(|| format!("there's no function with id {}", quad.arg1))?;
+                if quad.op() == QuadOperator::Memory {
+                    *callee = Some(function);
+                }
+                Ok(())
+            }
+            QuadOperator::Param if quad.arg2.is_some() => {
+                self.check_operand(quad.arg1, frame)?;
+                let callee = callee.ok_or("PARAM must follow a MEMORY quad".to_string())?;
+                match self.layout.decode(arg2()?) {
+                    Some(segment) if segment.scope == Scope::Local => {
+                        self.check_operand(arg2()?, Some(&callee.vars_amount))
+                    }
+                    _ => Err(format!("{} is not a param of {}", arg2()?, callee.name)),
+                }
+            }
+            QuadOperator::Print | QuadOperator::Param => self.check_operand(quad.arg1, frame),
+            QuadOperator::CallBuiltin => {
+                arg2()?;
+                self.check_writable(result()?, frame)
+            }
+            QuadOperator::Assign => {
+                self.check_writable(quad.arg1, frame)?;
+                self.check_operand(arg2()?, frame)
+            }
+            QuadOperator::EndFunc | QuadOperator::EndProgram => Ok(()),
+            _ => {
+                self.check_operand(quad.arg1, frame)?;
+                self.check_operand(arg2()?, frame)?;
+                self.check_writable(result()?, frame)
+            }
+        }
+    }
+
+    // An address has to be in a segment and, unless it's a constant, inside
+    // the memory the globals or the current frame get
+    fn check_operand(&self, address: i32, frame: Option<&Vec<Vec<i32>>>) -> Result<(), String> {
+        let segment = self
+            .layout
+            .decode(address)
+            .ok_or_else(|| format!("address {} is not in any segment", address))?;
+        let sizes = match segment.scope {
+            Scope::Constant if self.constants.get(address).is_some() => return Ok(()),
+            Scope::Constant => return Err(format!("there's no constant at {}", address)),
+            Scope::Global => &self.globals,
+            Scope::Local => frame
+                .ok_or_else(|| format!("local address {} is used outside a function", address))?,
+            Scope::Temp => frame.unwrap_or(&self.globals),
+        };
+        let type_index = [Type::Int, Type::Float, Type::Bool]
+            .iter()
+            .position(|var_type| *var_type == segment.var_type);
+        let size = type_index
+            .and_then(|type_index| sizes.get(type_index))
+            .and_then(|type_sizes| type_sizes.get((segment.scope == Scope::Temp) as usize))
+            .copied()
+            .unwrap_or(0);
+        if segment.offset(address) >= size as usize {
+            return Err(format!("address {} is outside its memory", address));
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self, address: i32, frame: Option<&Vec<Vec<i32>>>) -> Result<(), String> {
+        match self.layout.decode(address) {
+            Some(segment) if segment.scope == Scope::Constant => {
+                Err(format!("address {} can't be written", address))
+            }
+            _ => self.check_operand(address, frame),
+        }
+    }
+
+    // Functions ordered by start address, so the output is stable
+    fn sorted_functions(&self) -> Vec<(Option<i32>, &FunctionInfo)> {
+        let mut functions: Vec<(Option<i32>, &FunctionInfo)> = self
+            .functions
+            .values()
+            .map(|function| {
+                let id = self
+                    .function_ids
+                    .iter()
+                    .find(|(_, name)| **name == function.name)
+                    .map(|(id, _)| *id);
+                (id, function)
+            })
+            .collect();
+        functions.sort_by_key(|(_, function)| (function.start_address, function.name.clone()));
+        functions
+    }
+
+    fn variable_value(&self, var_type: &VarType, address: i32) -> VarValue {
+        match (self.constants.get(address), var_type) {
+            (Some(ConstValue::Int(value)), _) => VarValue::Int(*value),
+            (Some(ConstValue::Float(value)), _) => VarValue::Float(*value),
+            (_, VarType::Int) => VarValue::Int(0),
+            (_, VarType::Float) => VarValue::Float(0.0),
+        }
+    }
+
+    /// Line based form. Each line starts with the kind of entry it holds:
+    /// `segment`, `const`, `globals`, `function`, `param`, `var`, `builtin`
+    /// or `quad`.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!("{} {}", TEXT_HEADER, FORMAT_VERSION)];
+        for segment in self.layout.segments() {
+            lines.push(format!(
+                "segment {} {} {} {}",
+                type_name(&segment.var_type),
+                segment.scope,
+                segment.base,
+                segment.capacity
+            ));
+        }
+        for line in self.constants.to_text().lines() {
+            lines.push(format!("const {}", line));
+        }
+        lines.push(format!("globals {}", join_sizes(&self.globals)));
+
+        for (id, function) in self.sorted_functions() {
+            let id = id.map_or("-".to_string(), |id| id.to_string());
+            lines.push(format!(
+                "function {} {} {} {}",
+                function.name,
+                id,
+                function.start_address,
+                join_sizes(&function.vars_amount)
+            ));
+            for param in &function.params {
+                lines.push(format!(
+                    "param {} {} {}",
+                    function.name,
+                    param.name,
+                    var_type_name(&param.var_type)
+                ));
+            }
+            let mut vars: Vec<&VariableInfo> = function.vars.values().collect();
+            vars.sort_by_key(|var| var.address);
+            for var in vars {
+                lines.push(format!(
+                    "var {} {} {} {}",
+                    function.name,
+                    var.name,
+                    var_type_name(&var.var_type),
+                    var.address
+                ));
+            }
+        }
+
+        for (id, key) in self.called_builtins() {
+            lines.push(format!("builtin {} {}", id, key));
+        }
+
+        for quad in self.quadruplets.iter() {
+            let optional = |value: Option<i32>| value.map_or("_".to_string(), |v| v.to_string());
+            lines.push(format!(
+                "quad {} {} {} {}",
+                quad.op().name(),
+                quad.arg1,
+                optional(quad.arg2),
+                optional(quad.result)
+            ));
+        }
+
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
+    }
+
+    pub fn from_text(text: &str, builtins: &BuiltinRegistry) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().unwrap_or_default();
+        match header.split_once(' ') {
+            Some((TEXT_HEADER, version)) => check_version(parse(version, "version")?)?,
+            _ => return Err("Invalid object file header".to_string()),
+        }
+
+        let mut segments = vec![];
+        let mut constants = String::new();
+        let mut globals = vec![];
+        let mut functions = FunctionTable::new();
+        let mut function_ids = HashMap::new();
+        let mut vars = vec![];
+        let mut keys = BTreeMap::new();
+        let mut quadruplets = QuadrupletList::new();
+
+        for line in lines {
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            let field = |index: usize| {
+                fields
+                    .get(index)
+                    .copied()
+                    .ok_or_else(|| format!("Missing fields in: {}", line))
+            };
+
+            match kind {
+                "segment" => segments.push(Segment::new(
+                    parse_type(field(0)?)?,
+                    parse_scope(field(1)?)?,
+                    parse(field(2)?, "base")?,
+                    parse(field(3)?, "capacity")?,
+                )),
+                "const" => {
+                    constants.push_str(rest);
+                    constants.push('\n');
+                }
+                "globals" => globals = parse_sizes(&fields)?,
+                "function" => {
+                    let name = field(0)?.to_string();
+                    if field(1)? != "-" {
+                        function_ids.insert(parse(field(1)?, "function id")?, name.clone());
+                    }
+                    functions.insert(
+                        name.clone(),
+                        FunctionInfo {
+                            name,
+                            params: vec![],
+                            vars: HashMap::new(),
+                            vars_amount: parse_sizes(&fields[3.min(fields.len())..])?,
+                            start_address: parse(field(2)?, "start address")?,
+                        },
+                    );
+                }
+                "param" => {
+                    let function = functions
+                        .get_mut(field(0)?)
+                        .ok_or_else(|| format!("Param of unknown function: {}", line))?;
+                    function.params.push(FunctionParam {
+                        name: field(1)?.to_string(),
+                        var_type: parse_var_type(field(2)?)?,
+                    });
+                }
+                "var" => vars.push((
+                    field(0)?.to_string(),
+                    field(1)?.to_string(),
+                    parse_var_type(field(2)?)?,
+                    parse(field(3)?, "address")?,
+                )),
+                "builtin" => {
+                    keys.insert(parse(field(0)?, "builtin id")?, field(1)?.to_string());
+                }
+                "quad" => {
+                    let optional = |value: &str| match value {
+                        "_" => Ok(None),
+                        value => parse(value, "operand").map(Some),
+                    };
+                    let op = QuadOperator::from_name(field(0)?)
+                        .ok_or_else(|| format!("Unknown operator in: {}", line))?;
+                    quadruplets.push(Quadruplet::new(
+                        op.code(),
+                        parse(field(1)?, "operand")?,
+                        optional(field(2)?)?,
+                        optional(field(3)?)?,
+                    ));
+                }
+                _ => return Err(format!("Unknown entry: {}", line)),
+            }
+        }
+
+        let layout = MemoryLayout::new(segments)?;
+        let mut object = CompiledProgram {
+            constants: ConstantPool::from_text(&constants, &layout)?,
+            layout,
+            functions,
+            function_ids,
+            globals,
+            quadruplets,
+            builtins: BuiltinRegistry::new(),
+        };
+        object.add_vars(vars)?;
+        object.resolve_builtins(keys, builtins)?;
+        object.validate()?;
+        Ok(object)
+    }
+
+    fn add_vars(&mut self, vars: Vec<(String, String, VarType, i32)>) -> Result<(), String> {
+        for (function, name, var_type, address) in vars {
+            let value = self.variable_value(&var_type, address);
+            let function = self
+                .functions
+                .get_mut(&function)
+                .ok_or_else(|| format!("Variable {} of unknown function {}", name, function))?;
+            function.vars.insert(
+                name.clone(),
+                VariableInfo {
+                    name,
+                    value,
+                    var_type,
+                    address,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Compact form with the same entries as the text form, numbers stored
+    /// as little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(BINARY_MAGIC);
+        writer.u32(FORMAT_VERSION);
+
+        writer.u32(self.layout.segments().len() as u32);
+        for segment in self.layout.segments() {
+            writer.u8(TYPES.iter().position(|t| *t == segment.var_type).unwrap() as u8);
+            writer.u8(SCOPES.iter().position(|s| *s == segment.scope).unwrap() as u8);
+            writer.i32(segment.base);
+            writer.i32(segment.capacity);
+        }
+
+        writer.u32(self.constants.len() as u32);
+        for (address, value) in self.constants.iter() {
+            writer.i32(address);
+            match value {
+                ConstValue::Int(value) => {
+                    writer.u8(0);
+                    writer.bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                ConstValue::Float(value) => {
+                    writer.u8(1);
+                    writer.bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                ConstValue::String(value) => {
+                    writer.u8(2);
+                    writer.string(value);
+                }
+            }
+        }
+
+        writer.sizes(&self.globals);
+
+        let functions = self.sorted_functions();
+        writer.u32(functions.len() as u32);
+        for (id, function) in functions {
+            writer.string(&function.name);
+            writer.i32(id.unwrap_or(-1));
+            writer.i32(function.start_address);
+            writer.sizes(&function.vars_amount);
+            writer.u32(function.params.len() as u32);
+            for param in &function.params {
+                writer.string(&param.name);
+                writer.u8(var_type_code(&param.var_type));
+            }
+            let mut vars: Vec<&VariableInfo> = function.vars.values().collect();
+            vars.sort_by_key(|var| var.address);
+            writer.u32(vars.len() as u32);
+            for var in vars {
+                writer.string(&var.name);
+                writer.u8(var_type_code(&var.var_type));
+                writer.i32(var.address);
+            }
+        }
+
+        let called = self.called_builtins();
+        writer.u32(called.len() as u32);
+        for (id, key) in called {
+            writer.i32(id);
+            writer.string(&key);
+        }
+
+        writer.u32(self.quadruplets.len() as u32);
+        for quad in self.quadruplets.iter() {
+            writer.u8(quad.operator as u8);
+            writer.u8(quad.arg2.is_some() as u8 | (quad.result.is_some() as u8) << 1);
+            writer.i32(quad.arg1);
+            quad.arg2.into_iter().for_each(|arg2| writer.i32(arg2));
+            quad.result
+                .into_iter()
+                .for_each(|result| writer.i32(result));
+        }
+        writer.bytes
+    }
+
+    pub fn from_bytes(data: &[u8], builtins: &BuiltinRegistry) -> Result<Self, String> {
+        let mut reader = Reader { data, position: 0 };
+        if reader.take(4)? != BINARY_MAGIC {
+            return Err("Invalid object file header".to_string());
+        }
+        check_version(reader.u32()?)?;
+
+        let mut segments = vec![];
+        for _ in 0..reader.u32()? {
+            let var_type = TYPES.get(reader.u8()? as usize).cloned();
+            let scope = SCOPES.get(reader.u8()? as usize).copied();
+            match (var_type, scope) {
+                (Some(var_type), Some(scope)) => {
+                    segments.push(Segment::new(var_type, scope, reader.i32()?, reader.i32()?))
+                }
+                _ => return Err("Invalid segment".to_string()),
+            }
+        }
+        let layout = MemoryLayout::new(segments)?;
+
+        let mut constants = ConstantPool::with_layout(&layout);
+        for _ in 0..reader.u32()? {
+            let address = reader.i32()?;
+            let value = match reader.u8()? {
+                0 => ConstValue::Int(i64::from_le_bytes(reader.take(8)?.try_into().unwrap())),
+                1 => ConstValue::Float(f64::from_le_bytes(reader.take(8)?.try_into().unwrap())),
+                2 => ConstValue::String(reader.string()?),
+                _ => return Err("Invalid constant".to_string()),
+            };
+            if constants.insert(value)? != address {
+                return Err(format!(
+                    "Constant at {} is out of order or repeated",
+                    address
+                ));
+            }
+        }
+
+        let globals = reader.sizes()?;
+
+        let mut functions = FunctionTable::new();
+        let mut function_ids = HashMap::new();
+        let mut vars = vec![];
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let id = reader.i32()?;
+            if id >= 0 {
+                function_ids.insert(id, name.clone());
+            }
+            let start_address = reader.i32()?;
+            let vars_amount = reader.sizes()?;
+            let mut params = vec![];
+            for _ in 0..reader.u32()? {
+                params.push(FunctionParam {
+                    name: reader.string()?,
+                    var_type: parse_var_type_code(reader.u8()?)?,
+                });
+            }
+            for _ in 0..reader.u32()? {
+                vars.push((
+                    name.clone(),
+                    reader.string()?,
+                    parse_var_type_code(reader.u8()?)?,
+                    reader.i32()?,
+                ));
+            }
+            functions.insert(
+                name.clone(),
+                FunctionInfo {
+                    name,
+                    params,
+                    vars: HashMap::new(),
+                    vars_amount,
+                    start_address,
+                },
+            );
+        }
+
+        let mut keys = BTreeMap::new();
+        for _ in 0..reader.u32()? {
+            keys.insert(reader.i32()?, reader.string()?);
+        }
+
+        let mut quadruplets = QuadrupletList::new();
+        for _ in 0..reader.u32()? {
+            let operator = reader.u8()? as i32;
+            if !(1..=18).contains(&operator) {
+                return Err(format!("Unknown operator code {}", operator));
+            }
+            let flags = reader.u8()?;
+            let arg1 = reader.i32()?;
+            let arg2 = if flags & 1 != 0 {
+                Some(reader.i32()?)
+            } else {
+                None
+            };
+            let result = if flags & 2 != 0 {
+                Some(reader.i32()?)
+            } else {
+                None
+            };
+            quadruplets.push(Quadruplet::new(operator, arg1, arg2, result));
+        }
+
+        let mut object = CompiledProgram {
+            layout,
+            constants,
+            functions,
+            function_ids,
+            globals,
+            quadruplets,
+            builtins: BuiltinRegistry::new(),
+        };
+        object.add_vars(vars)?;
+        object.resolve_builtins(keys, builtins)?;
+        object.validate()?;
+        Ok(object)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "Object file version {} is not compatible with version {}",
+            version, FORMAT_VERSION
+        ));
+    }
+    Ok(())
+}
+
+fn parse<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid {}: {}", what, value))
+}
+
+fn parse_type(name: &str) -> Result<Type, String> {
+    TYPES
+        .iter()
+        .find(|var_type| type_name(var_type) == name)
+        .cloned()
+        .ok_or_else(|| format!("Invalid type: {}", name))
+}
+
+fn parse_scope(name: &str) -> Result<Scope, String> {
+    SCOPES
+        .iter()
+        .find(|scope| scope.to_string() == name)
+        .copied()
+        .ok_or_else(|| format!("Invalid scope: {}", name))
+}
+
+fn var_type_name(var_type: &VarType) -> &'static str {
+    match var_type {
+        VarType::Int => "int",
+        VarType::Float => "float",
+    }
+}
+
+fn parse_var_type(name: &str) -> Result<VarType, String> {
+    match name {
+        "int" => Ok(VarType::Int),
+        "float" => Ok(VarType::Float),
+        _ => Err(format!("Invalid variable type: {}", name)),
+    }
+}
+
+fn var_type_code(var_type: &VarType) -> u8 {
+    match var_type {
+        VarType::Int => 0,
+        VarType::Float => 1,
+    }
+}
+
+fn parse_var_type_code(code: u8) -> Result<VarType, String> {
+    match code {
+        0 => Ok(VarType::Int),
+        1 => Ok(VarType::Float),
+        _ => Err(format!("Invalid variable type code: {}", code)),
+    }
+}
+
+// Sizes in the shape of `vars_amount`, written as pairs of (global or local, temp)
+fn join_sizes(sizes: &[Vec<i32>]) -> String {
+    sizes
+        .iter()
+        .flatten()
+        .map(|size| size.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_sizes(fields: &[&str]) -> Result<Vec<Vec<i32>>, String> {
+    let sizes = fields
+        .iter()
+        .map(|field| parse(field, "size"))
+        .collect::<Result<Vec<i32>, String>>()?;
+    Ok(sizes.chunks(2).map(|pair| pair.to_vec()).collect())
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn sizes(&mut self, sizes: &[Vec<i32>]) {
+        let sizes: Vec<i32> = sizes.iter().flatten().copied().collect();
+        self.u32(sizes.len() as u32);
+        sizes.into_iter().for_each(|size| self.i32(size));
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.position + count;
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or_else(|| "Unexpected end of object file".to_string())?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| "Invalid string".to_string())
+    }
+
+    fn sizes(&mut self) -> Result<Vec<Vec<i32>>, String> {
+        let sizes = (0..self.u32()?)
+            .map(|_| self.i32())
+            .collect::<Result<Vec<i32>, String>>()?;
+        Ok(sizes.chunks(2).map(|pair| pair.to_vec()).collect())
+    }
+}
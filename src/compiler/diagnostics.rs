@@ -0,0 +1,70 @@
+use crate::compiler::tokens::{LexicalError, Token};
+use lalrpop_util::ParseError;
+use std::fmt::{Display, Formatter, Result};
+
+/// A semantic error found while compiling, with the byte offset of the
+/// token that produced it when one is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub position: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, position: Option<usize>) -> Self {
+        Diagnostic { message, position }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.position {
+            Some(position) => write!(f, "error at {}: {}", position, self.message),
+            None => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+// The parser stops at the first syntax error, which is reported after the
+// semantic errors found before it
+impl From<ParseError<usize, Token, LexicalError>> for Diagnostic {
+    fn from(error: ParseError<usize, Token, LexicalError>) -> Self {
+        let expected = |expected: Vec<String>| match expected.as_slice() {
+            [] => String::new(),
+            expected => format!(", expected one of {}", expected.join(", ")),
+        };
+        match error {
+            ParseError::InvalidToken { location } => {
+                Diagnostic::new("Invalid token".to_string(), Some(location))
+            }
+            ParseError::UnrecognizedEof {
+                location,
+                expected: tokens,
+            } => Diagnostic::new(
+                format!("Unexpected end of file{}", expected(tokens)),
+                Some(location),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (start, token, _),
+                expected: tokens,
+            } => Diagnostic::new(
+                format!("Unexpected {}{}", token, expected(tokens)),
+                Some(start),
+            ),
+            ParseError::ExtraToken {
+                token: (start, token, _),
+            } => Diagnostic::new(
+                format!("Unexpected {} after the end of the program", token),
+                Some(start),
+            ),
+            ParseError::User { error } => {
+                let message = match error {
+                    LexicalError::InvalidInteger(error) => format!("Invalid integer: {}", error),
+                    LexicalError::InvalidFloat(error) => format!("Invalid float: {}", error),
+                    LexicalError::InvalidToken => "Invalid token".to_string(),
+                };
+                Diagnostic::new(message, None)
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use crate::compiler::compiled_program::CompiledProgram;
+use crate::compiler::memory_layout::Scope;
+use crate::compiler::quadruplets::{QuadOperator, Quadruplet};
+use crate::compiler::semantic_tables::{FunctionInfo, VarType};
+use crate::compiler::value_table::{type_name, ConstValue};
+use crate::optimizer::function_ranges;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Names of the addresses a function can reach.
+struct Names<'a> {
+    object: &'a CompiledProgram,
+    function: Option<&'a FunctionInfo>,
+    temps: HashMap<i32, String>,
+}
+
+impl Names<'_> {
+    // Named constants share their address with the literal, so they're shown
+    // as the literal value
+    fn operand(&self, address: i32) -> String {
+        if let Some(value) = self.object.constants.get(address) {
+            return literal(value);
+        }
+        let local = self
+            .function
+            .and_then(|function| variable(function, address));
+        let global = self
+            .object
+            .functions
+            .get("main")
+            .and_then(|main| variable(main, address));
+        self.temps
+            .get(&address)
+            .cloned()
+            .or(local)
+            .or(global)
+            .unwrap_or_else(|| format!("@{}", address))
+    }
+}
+
+fn literal(value: &ConstValue) -> String {
+    match value {
+        ConstValue::Int(value) => value.to_string(),
+        ConstValue::Float(value) => format!("{:?}", value),
+        ConstValue::String(value) => format!("{:?}", value),
+    }
+}
+
+fn variable(function: &FunctionInfo, address: i32) -> Option<String> {
+    function
+        .vars
+        .values()
+        .find(|var| var.address == address)
+        .map(|var| var.name.clone())
+}
+
+fn var_type_name(var_type: &VarType) -> &'static str {
+    match var_type {
+        VarType::Int => "int",
+        VarType::Float => "float",
+    }
+}
+
+/// A quad as the disassembler shows it, with the name of the address it
+/// writes, if any. `PARAM` writes the parameter of the function being called.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedQuad {
+    pub instruction: String,
+    pub target: Option<String>,
+    pub writes_temp: bool,
+}
+
+// Functions are labelled by name, every other target gets an `L` label
+fn labels(object: &CompiledProgram) -> HashMap<i32, String> {
+    let mut labels: HashMap<i32, String> = HashMap::new();
+    for function in object.functions.values() {
+        labels.insert(function.start_address, function.name.clone());
+    }
+    let targets: BTreeSet<i32> = object
+        .quadruplets
+        .iter()
+        .filter_map(|quad| quad.jump_target())
+        .filter(|target| !labels.contains_key(target))
+        .collect();
+    for (number, target) in targets.into_iter().enumerate() {
+        labels.insert(target, format!("L{}", number + 1));
+    }
+    labels
+}
+
+/// Every quad of a program, named the way `disassemble` shows it.
+pub fn decode(object: &CompiledProgram) -> Vec<DecodedQuad> {
+    let quadruplets = &object.quadruplets;
+    let ranges = function_ranges(quadruplets, &object.functions);
+    let labels = labels(object);
+    let label = |target: i32| {
+        labels
+            .get(&target)
+            .cloned()
+            .unwrap_or_else(|| format!("@{}", target))
+    };
+
+    let mut decoded = vec![];
+    let mut names = Names {
+        object,
+        function: None,
+        temps: HashMap::new(),
+    };
+    let mut callee: Option<&FunctionInfo> = None;
+    for i in 0..quadruplets.len() {
+        if let Some((name, range)) = ranges.iter().find(|(_, range)| range.start == i) {
+            names.function = Some(&object.functions[name]);
+            names.temps = temp_names(object, range.clone());
+        }
+
+        let quad = quadruplets.get(i).unwrap();
+        if quad.op() == QuadOperator::Memory {
+            callee = function_by_id(object, quad.arg1);
+        }
+        let (target, writes_temp) = match (quad.op(), quad.writes()) {
+            (QuadOperator::Param, _) if quad.arg2.is_none() => (None, false),
+            (QuadOperator::Param, _) => {
+                let param = quad.arg2.unwrap_or(-1);
+                let name = callee
+                    .and_then(|callee| variable(callee, param))
+                    .unwrap_or_else(|| format!("@{}", param));
+                (Some(name), false)
+            }
+            (_, Some(address)) => (
+                Some(names.operand(address)),
+                names.temps.contains_key(&address),
+            ),
+            _ => (None, false),
+        };
+        decoded.push(DecodedQuad {
+            instruction: instruction(quad, &names, callee, &label),
+            target,
+            writes_temp,
+        });
+
+        if ranges.iter().any(|(_, range)| range.end == i + 1) {
+            names.function = None;
+            names.temps.clear();
+        }
+    }
+    decoded
+}
+
+/// Readable listing of a compiled program. Addresses are shown as variable
+/// names, literal values and temps `t1`, `t2`, ... numbered per function, and
+/// jump targets as labels. Each function starts with a header holding its
+/// directory entry, which is what `assembler::assemble` reads back.
+pub fn disassemble(object: &CompiledProgram) -> String {
+    let quadruplets = &object.quadruplets;
+    let ranges = function_ranges(quadruplets, &object.functions);
+    let labels = labels(object);
+    let decoded = decode(object);
+
+    let mut text = String::new();
+    for i in 0..quadruplets.len() {
+        if let Some((name, range)) = ranges.iter().find(|(_, range)| range.start == i) {
+            let temps = temp_names(object, range.clone());
+            write_header(&mut text, object, &object.functions[name], &temps);
+        }
+        if let Some(name) = labels.get(&i) {
+            writeln!(text, "{}:", name).unwrap();
+        }
+
+        let instruction = &decoded[i as usize].instruction;
+        writeln!(text, "    {:<40} ; {}", instruction, i).unwrap();
+
+        if ranges.iter().any(|(_, range)| range.end == i + 1) {
+            writeln!(text, "end").unwrap();
+            writeln!(text).unwrap();
+        }
+    }
+    text
+}
+
+fn function_by_id(object: &CompiledProgram, id: i32) -> Option<&FunctionInfo> {
+    object
+        .function_ids
+        .get(&id)
+        .and_then(|name| object.functions.get(name))
+}
+
+// Temps numbered by their first appearance in the function
+fn temp_names(object: &CompiledProgram, range: std::ops::Range<i32>) -> HashMap<i32, String> {
+    let mut temps = HashMap::new();
+    for i in range {
+        let quad = object.quadruplets.get(i).unwrap();
+        for address in quad.reads().into_iter().chain(quad.writes()) {
+            let is_temp = object
+                .layout
+                .decode(address)
+                .is_some_and(|segment| segment.scope == Scope::Temp);
+            if is_temp && !temps.contains_key(&address) {
+                temps.insert(address, format!("t{}", temps.len() + 1));
+            }
+        }
+    }
+    temps
+}
+
+fn write_header(
+    text: &mut String,
+    object: &CompiledProgram,
+    function: &FunctionInfo,
+    temps: &HashMap<i32, String>,
+) {
+    let id = object
+        .function_ids
+        .iter()
+        .find(|(_, name)| **name == function.name)
+        .map_or(String::new(), |(id, _)| format!(" id {}", id));
+    let frame: Vec<String> = function
+        .vars_amount
+        .iter()
+        .flatten()
+        .map(|size| size.to_string())
+        .collect();
+    writeln!(
+        text,
+        "function {}{} frame {}",
+        function.name,
+        id,
+        frame.join(" ")
+    )
+    .unwrap();
+
+    for param in &function.params {
+        writeln!(
+            text,
+            "    param {} {}",
+            param.name,
+            var_type_name(&param.var_type)
+        )
+        .unwrap();
+    }
+    let mut vars: Vec<_> = function.vars.values().collect();
+    vars.sort_by_key(|var| var.address);
+    for var in vars {
+        let var_type = var_type_name(&var.var_type);
+        match object.constants.get(var.address) {
+            Some(value) => writeln!(
+                text,
+                "    const {} {} {}",
+                var.name,
+                var_type,
+                literal(value)
+            ),
+            None => writeln!(text, "    var {} {} @{}", var.name, var_type, var.address),
+        }
+        .unwrap();
+    }
+    let mut temps: Vec<(&i32, &String)> = temps.iter().collect();
+    temps.sort_by_key(|(_, name)| name[1..].parse::<usize>().unwrap());
+    for (address, name) in temps {
+        let var_type = &object.layout.decode(*address).unwrap().var_type;
+        writeln!(
+            text,
+            "    temp {} {} @{}",
+            name,
+            type_name(var_type),
+            address
+        )
+        .unwrap();
+    }
+}
+
+fn instruction(
+    quad: &Quadruplet,
+    names: &Names,
+    callee: Option<&FunctionInfo>,
+    label: &impl Fn(i32) -> String,
+) -> String {
+    let op = quad.op();
+    let name = op.name();
+    match op {
+        QuadOperator::Goto => format!("{} {}", name, label(quad.arg1)),
+        QuadOperator::GotoF | QuadOperator::GotoV => format!(
+            "{} {}, {}",
+            name,
+            names.operand(quad.arg1),
+            label(quad.arg2.unwrap_or(-1))
+        ),
+        QuadOperator::Assign => format!(
+            "{} {}, {}",
+            name,
+            names.operand(quad.arg1),
+            names.operand(quad.arg2.unwrap_or(-1))
+        ),
+        QuadOperator::Print => format!("{} {}", name, names.operand(quad.arg1)),
+        QuadOperator::Memory | QuadOperator::GoSub => {
+            let function = names
+                .object
+                .function_ids
+                .get(&quad.arg1)
+                .cloned()
+                .unwrap_or_else(|| format!("@{}", quad.arg1));
+            format!("{} {}", name, function)
+        }
+        QuadOperator::Param if quad.arg2.is_none() => {
+            format!("{} {}", name, names.operand(quad.arg1))
+        }
+        QuadOperator::CallBuiltin => {
+            let builtin = names
+                .object
+                .builtins
+                .get(quad.arg1)
+                .map_or_else(|| format!("@{}", quad.arg1), |builtin| builtin.key());
+            format!(
+                "{} {} -> {}",
+                name,
+                builtin,
+                names.operand(quad.result.unwrap_or(-1))
+            )
+        }
+        QuadOperator::Param => {
+            let param = quad.arg2.unwrap_or(-1);
+            let param = callee
+                .and_then(|callee| variable(callee, param))
+                .unwrap_or_else(|| format!("@{}", param));
+            format!("{} {} -> {}", name, names.operand(quad.arg1), param)
+        }
+        QuadOperator::EndFunc | QuadOperator::EndProgram => name.to_string(),
+        _ => format!(
+            "{} {}, {} -> {}",
+            name,
+            names.operand(quad.arg1),
+            names.operand(quad.arg2.unwrap_or(-1)),
+            names.operand(quad.result.unwrap_or(-1))
+        ),
+    }
+}
@@ -0,0 +1,123 @@
+use crate::compiler::semantic_cube::Type;
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Global,
+    Local,
+    Temp,
+    Constant,
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let name = match self {
+            Scope::Global => "global",
+            Scope::Local => "local",
+            Scope::Temp => "temp",
+            Scope::Constant => "const",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A contiguous range of virtual addresses holding values of one type and scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub var_type: Type,
+    pub scope: Scope,
+    pub base: i32,
+    pub capacity: i32,
+}
+
+impl Segment {
+    pub fn new(var_type: Type, scope: Scope, base: i32, capacity: i32) -> Self {
+        Segment {
+            var_type,
+            scope,
+            base,
+            capacity,
+        }
+    }
+
+    pub fn end(&self) -> i32 {
+        self.base + self.capacity
+    }
+
+    pub fn contains(&self, address: i32) -> bool {
+        (self.base..self.end()).contains(&address)
+    }
+
+    pub fn offset(&self, address: i32) -> usize {
+        (address - self.base) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryLayout {
+    segments: Vec<Segment>,
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        MemoryLayout::new(vec![
+            Segment::new(Type::Int, Scope::Global, 1000, 1000),
+            Segment::new(Type::Int, Scope::Local, 2000, 1000),
+            Segment::new(Type::Int, Scope::Temp, 3000, 1000),
+            Segment::new(Type::Float, Scope::Global, 4000, 1000),
+            Segment::new(Type::Float, Scope::Local, 5000, 1000),
+            Segment::new(Type::Float, Scope::Temp, 6000, 2000),
+            Segment::new(Type::Bool, Scope::Temp, 8000, 1000),
+            Segment::new(Type::Int, Scope::Constant, 9000, 1000),
+            Segment::new(Type::Float, Scope::Constant, 10000, 1000),
+            Segment::new(Type::String, Scope::Constant, 11000, 1000),
+        ])
+        .unwrap()
+    }
+}
+
+impl MemoryLayout {
+    /// Builds a layout, rejecting repeated (type, scope) pairs and
+    /// overlapping address ranges.
+    pub fn new(segments: Vec<Segment>) -> std::result::Result<Self, String> {
+        for (i, segment) in segments.iter().enumerate() {
+            if segment.capacity < 0 {
+                return Err(format!(
+                    "Segment {} {:?} has a negative capacity",
+                    segment.scope, segment.var_type
+                ));
+            }
+            for other in &segments[i + 1..] {
+                if segment.var_type == other.var_type && segment.scope == other.scope {
+                    return Err(format!(
+                        "Segment {} {:?} is declared twice",
+                        segment.scope, segment.var_type
+                    ));
+                }
+                if segment.base < other.end() && other.base < segment.end() {
+                    return Err(format!(
+                        "Segments {} {:?} and {} {:?} overlap",
+                        segment.scope, segment.var_type, other.scope, other.var_type
+                    ));
+                }
+            }
+        }
+        Ok(MemoryLayout { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn segment(&self, var_type: &Type, scope: Scope) -> Option<&Segment> {
+        self.segments
+            .iter()
+            .find(|segment| segment.var_type == *var_type && segment.scope == scope)
+    }
+
+    pub fn decode(&self, address: i32) -> Option<&Segment> {
+        self.segments
+            .iter()
+            .find(|segment| segment.contains(address))
+    }
+}
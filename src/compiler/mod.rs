@@ -1,4 +1,10 @@
+pub mod assembler;
+pub mod builtins;
+pub mod compiled_program;
+pub mod diagnostics;
+pub mod disassembler;
 pub mod lexer;
+pub mod memory_layout;
 pub mod program_manager;
 pub mod quadruplets;
 pub mod semantic_cube;
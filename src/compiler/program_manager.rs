@@ -1,13 +1,18 @@
+use crate::compiler::builtins::BuiltinRegistry;
+use crate::compiler::diagnostics::Diagnostic;
+use crate::compiler::memory_layout::{MemoryLayout, Scope};
 use crate::compiler::quadruplets::{Quadruplet, QuadrupletList};
 use crate::compiler::semantic_cube::{CuboSemantico, Operator, Type};
-use crate::compiler::semantic_tables::FunctionTable;
-use crate::compiler::value_table::ValueTable;
+use crate::compiler::semantic_tables::{
+    FunctionCall, FunctionTable, VarType, VarValue, VariableInfo,
+};
+use crate::compiler::value_table::{type_name, ConstValue, ValueTable};
 use crate::Stack;
 use core::panic;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::quadruplets::{convert_quad_op_to_code, QuadOperator};
-use super::value_table::{Memory, Value};
+use super::quadruplets::{convert_quad_op_to_code, convert_semantic_op_to_quad_op, QuadOperator};
+use super::value_table::Value;
 
 pub struct ProgramManager {
     pub cubo: CuboSemantico,
@@ -20,45 +25,414 @@ pub struct ProgramManager {
     pub instruction_pointer: i32,
     pub jumps_stack: Vec<i32>,
     pub curr_function: Stack<String>,
-    pub memory_stack: Stack<Memory>,
     pub function_ids: HashMap<i32, String>,
-    position_before_fcall: Stack<i32>,
-    upcoming_function: Option<Memory>,
+    pub fcall_stack: Stack<FunctionCall>,
+    pub pending_calls: Vec<FunctionCall>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub builtins: BuiltinRegistry,
+    marked_quads: i32,
+    // Constants whose value had an error, by function and name
+    invalid_constants: HashSet<(String, String)>,
+}
+
+impl Default for ProgramManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProgramManager {
     pub fn new() -> Self {
+        ProgramManager::with_layout(MemoryLayout::default())
+    }
+
+    pub fn with_layout(layout: MemoryLayout) -> Self {
         ProgramManager {
             cubo: CuboSemantico::new(),
             tabla_funciones: HashMap::new(),
             quadruplets: QuadrupletList::new(),
-            value_table: ValueTable::new(),
+            value_table: ValueTable::with_layout(layout),
             operand_stack: Vec::new(),
             operator_stack: Vec::new(),
             polish_vector: Vec::new(),
             instruction_pointer: 0,
             jumps_stack: Vec::new(),
             curr_function: Stack::new(),
-            memory_stack: Stack::new(),
             function_ids: HashMap::new(),
-            position_before_fcall: Stack::new(),
-            upcoming_function: None,
+            fcall_stack: Stack::new(),
+            pending_calls: Vec::new(),
+            diagnostics: Vec::new(),
+            builtins: BuiltinRegistry::standard(),
+            marked_quads: 0,
+            invalid_constants: HashSet::new(),
         }
     }
 
     pub fn new_temp(&mut self, var_type: Type) -> i32 {
         match var_type {
-            Type::Int => self
-                .value_table
-                .insert_integer(0, "temp", self.memory_stack.top()),
-            Type::Float => self
-                .value_table
-                .insert_float(0.0, "temp", self.memory_stack.top()),
-            Type::Bool => self.value_table.insert_bool(false, self.memory_stack.top()),
+            Type::Int | Type::Float | Type::Bool => self.allocate(var_type, Scope::Temp),
             _ => panic!("Invalid type"),
         }
     }
 
+    pub fn allocate(&mut self, var_type: Type, scope: Scope) -> i32 {
+        match self.value_table.allocate(var_type.clone(), scope) {
+            Ok(address) => address,
+            Err(message) => {
+                self.report_exhausted(message);
+                // Stay inside the segment so the address still decodes to its type
+                match self.value_table.layout.segment(&var_type, scope) {
+                    Some(segment) => segment.end() - 1,
+                    None => 0,
+                }
+            }
+        }
+    }
+
+    pub fn insert_constant(&mut self, value: ConstValue) -> i32 {
+        match self.value_table.constants.insert(value.clone()) {
+            Ok(address) => address,
+            Err(message) => {
+                self.report_exhausted(message);
+                let var_type = match value {
+                    ConstValue::Int(_) => Type::Int,
+                    ConstValue::Float(_) => Type::Float,
+                    ConstValue::String(_) => Type::String,
+                };
+                match self.value_table.layout.segment(&var_type, Scope::Constant) {
+                    Some(segment) => segment.end() - 1,
+                    None => 0,
+                }
+            }
+        }
+    }
+
+    fn report_exhausted(&mut self, message: String) {
+        if !self.diagnostics.iter().any(|d| d.message == message) {
+            self.diagnostics.push(Diagnostic::new(message, None));
+        }
+    }
+
+    pub fn find_variable(&self, name: &str) -> Option<&VariableInfo> {
+        let current_function = self.curr_function.top()?;
+        self.tabla_funciones
+            .get(current_function)
+            .and_then(|function| function.vars.get(name))
+            .or_else(|| self.tabla_funciones.get("main")?.vars.get(name))
+    }
+
+    pub fn declare_constant(
+        &mut self,
+        name: String,
+        var_type: Type,
+        value: Option<Value>,
+        position: usize,
+    ) {
+        let function_name = self.curr_function.top().unwrap().clone();
+        if self.tabla_funciones[&function_name]
+            .vars
+            .contains_key(&name)
+        {
+            self.diagnostics.push(Diagnostic::new(
+                format!("Constant {} is already declared", name),
+                Some(position),
+            ));
+            return;
+        }
+
+        let (address, value, var_type) = match (var_type, value) {
+            (Type::Int, Some(Value::Int(val))) => (
+                self.insert_constant(ConstValue::Int(val)),
+                VarValue::Int(val),
+                VarType::Int,
+            ),
+            (Type::Float, Some(Value::Float(val))) => (
+                self.insert_constant(ConstValue::Float(val)),
+                VarValue::Float(val),
+                VarType::Float,
+            ),
+            // The initializer already reported its own error
+            (_, None) => {
+                self.invalid_constants.insert((function_name, name));
+                return;
+            }
+            (var_type, Some(value)) => {
+                self.diagnostics.push(Diagnostic::new(
+                    format!(
+                        "Constant {} is declared as {:?} but its value is {:?}",
+                        name,
+                        var_type,
+                        value.value_type()
+                    ),
+                    Some(position),
+                ));
+                self.invalid_constants.insert((function_name, name));
+                return;
+            }
+        };
+
+        let var_info = VariableInfo {
+            name: name.clone(),
+            value,
+            var_type,
+            address,
+        };
+        self.tabla_funciones
+            .get_mut(&function_name)
+            .unwrap()
+            .vars
+            .insert(name, var_info);
+    }
+
+    /// Reports a name that isn't declared, unless it's a constant whose
+    /// value already had an error.
+    pub fn report_missing_variable(&mut self, name: &str, position: usize) {
+        if !self.is_invalid_constant(name) {
+            self.diagnostics.push(Diagnostic::new(
+                format!("There's no variable {}", name),
+                Some(position),
+            ));
+        }
+    }
+
+    fn is_invalid_constant(&self, name: &str) -> bool {
+        let current_function = self.curr_function.top().cloned().unwrap_or_default();
+        [current_function, "main".to_string()]
+            .into_iter()
+            .any(|function| {
+                self.invalid_constants
+                    .contains(&(function, name.to_string()))
+            })
+    }
+
+    pub fn constant_value(&mut self, name: &str, position: usize) -> Option<Value> {
+        let value = match self.find_variable(name) {
+            Some(info) if self.value_table.is_constant(info.address) => match info.value {
+                VarValue::Int(val) => Some(Value::Int(val)),
+                VarValue::Float(val) => Some(Value::Float(val)),
+                VarValue::Bool(val) => Some(Value::Bool(val)),
+            },
+            Some(_) => None,
+            None if self.is_invalid_constant(name) => return None,
+            None => {
+                self.diagnostics.push(Diagnostic::new(
+                    format!("There's no constant {}", name),
+                    Some(position),
+                ));
+                return None;
+            }
+        };
+
+        if value.is_none() {
+            self.diagnostics.push(Diagnostic::new(
+                format!(
+                    "{} is not a constant and can't be evaluated at compile time",
+                    name
+                ),
+                Some(position),
+            ));
+        }
+        value
+    }
+
+    pub fn fold_constant(
+        &mut self,
+        left: Option<Value>,
+        op: Operator,
+        right: Option<Value>,
+        position: usize,
+    ) -> Option<Value> {
+        match self.cubo.evaluate(&left?, op, &right?) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.diagnostics
+                    .push(Diagnostic::new(message, Some(position)));
+                None
+            }
+        }
+    }
+
+    /// Creates the quad of `left op right` and returns the temp that gets the
+    /// result. When the cube rejects the operand types, the error is reported
+    /// and a temp is returned without a quad so parsing can go on.
+    pub fn binary_operation(
+        &mut self,
+        left: i32,
+        op: Operator,
+        right: i32,
+        position: usize,
+    ) -> i32 {
+        let left_type = self.value_table.get_var_type(left);
+        let right_type = self.value_table.get_var_type(right);
+        let result_type = self
+            .cubo
+            .get_type(left_type.clone(), op, right_type.clone());
+        if result_type == Type::Error {
+            let symbol = match op {
+                Operator::Add => "+",
+                Operator::Subtract => "-",
+                Operator::Multiply => "*",
+                Operator::Divide => "/",
+                Operator::LessThan => "<",
+                Operator::GreaterThan => ">",
+                Operator::Equal => "==",
+                Operator::NotEqual => "!=",
+            };
+            self.diagnostics.push(Diagnostic::new(
+                format!(
+                    "Operator {} can't be applied to {} and {}",
+                    symbol,
+                    type_name(&left_type),
+                    type_name(&right_type)
+                ),
+                Some(position),
+            ));
+            let placeholder = match op {
+                Operator::LessThan
+                | Operator::GreaterThan
+                | Operator::Equal
+                | Operator::NotEqual => Type::Bool,
+                _ => Type::Int,
+            };
+            return self.new_temp(placeholder);
+        }
+        let result = self.new_temp(result_type);
+        self.create_quad(
+            convert_semantic_op_to_quad_op(op),
+            left,
+            Some(right),
+            Some(result),
+        );
+        result
+    }
+
+    pub fn function_id(&self, name: &str) -> Option<i32> {
+        self.function_ids
+            .iter()
+            .find(|(_, v)| v.as_str() == name)
+            .map(|(k, _)| *k)
+    }
+
+    /// Lets programs call a function of the application embedding Pandora,
+    /// which gets the arguments as `Value`s and returns a `Value`. It has to
+    /// be registered before parsing.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        params: Vec<Type>,
+        result: Type,
+        function: impl Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static,
+    ) -> Result<(), String> {
+        self.builtins.register_host(name, params, result, function)
+    }
+
+    /// Calls the builtin `name` with the operands pushed since
+    /// `operands_start`, each passed by a `PARAM` without a target, and
+    /// returns the temp that gets the result. When no signature fits, the
+    /// error is reported and the temp has the type of the first signature.
+    pub fn call_builtin(&mut self, name: &str, operands_start: usize, position: usize) -> i32 {
+        let arguments = self.operand_stack.split_off(operands_start);
+        let types: Vec<Type> = arguments
+            .iter()
+            .map(|argument| self.value_table.get_var_type(*argument))
+            .collect();
+
+        let resolved = match self.builtins.find_name(name) {
+            Some(_) => self.builtins.resolve(name, &types, &self.cubo),
+            None if self.tabla_funciones.contains_key(name) => Err(format!(
+                "Function {} doesn't return a value and can't be used in an expression",
+                name
+            )),
+            None => Err(format!("There's no builtin function {}", name)),
+        };
+        let id = match resolved {
+            Ok(id) => id,
+            Err(message) => {
+                self.diagnostics
+                    .push(Diagnostic::new(message, Some(position)));
+                let result = match self.builtins.find_name(name) {
+                    Some(id) => self.builtins.get(id).unwrap().result.clone(),
+                    None => Type::Int,
+                };
+                return self.new_temp(result);
+            }
+        };
+        for argument in &arguments {
+            self.create_quad(QuadOperator::Param, *argument, None, None);
+        }
+        let builtin = self.builtins.get(id).unwrap();
+        let (arity, result) = (builtin.params.len() as i32, builtin.result.clone());
+        let result = self.new_temp(result);
+        self.create_quad(QuadOperator::CallBuiltin, id, Some(arity), Some(result));
+        result
+    }
+
+    pub fn register_call(&mut self, call: FunctionCall) {
+        if self.function_id(&call.name).is_some() {
+            self.resolve_call(&call);
+        } else {
+            self.pending_calls.push(call);
+        }
+    }
+
+    pub fn resolve_pending_calls(&mut self) {
+        for call in std::mem::take(&mut self.pending_calls) {
+            if self.function_id(&call.name).is_some() {
+                self.resolve_call(&call);
+            } else {
+                self.diagnostics.push(Diagnostic::new(
+                    format!("Function {} is not declared", call.name),
+                    Some(call.position),
+                ));
+            }
+        }
+    }
+
+    fn resolve_call(&mut self, call: &FunctionCall) {
+        let function_id = self.function_id(&call.name).unwrap();
+        let function_info = self.tabla_funciones.get(&call.name).unwrap();
+
+        if function_info.params.len() != call.param_quads.len() {
+            self.diagnostics.push(Diagnostic::new(
+                format!(
+                    "Argument quantities doesn't match with function {} declaration",
+                    call.name
+                ),
+                Some(call.position),
+            ));
+            return;
+        }
+
+        let mut param_addresses = vec![];
+        for (param, quad_index) in function_info.params.iter().zip(&call.param_quads) {
+            let arg_address = self.quadruplets.get(*quad_index).unwrap().arg1;
+            let arg_type = match self.value_table.get_var_type(arg_address) {
+                Type::Int => Some(VarType::Int),
+                Type::Float => Some(VarType::Float),
+                _ => None,
+            };
+
+            if arg_type.as_ref() != Some(&param.var_type) {
+                self.diagnostics.push(Diagnostic::new(
+                    format!(
+                        "Argument and parameters are not the same type on function {}",
+                        call.name
+                    ),
+                    Some(call.position),
+                ));
+                return;
+            }
+
+            param_addresses.push(function_info.vars.get(&param.name).unwrap().address);
+        }
+
+        for (quad_index, param_address) in call.param_quads.iter().zip(param_addresses) {
+            self.fill_quad(*quad_index, param_address);
+        }
+        self.fill_quad(call.memory_quad, function_id);
+        self.fill_quad(call.gosub_quad, function_id);
+    }
+
     pub fn create_quad(
         &mut self,
         operator: QuadOperator,
@@ -66,44 +440,32 @@ impl ProgramManager {
         arg2: Option<i32>,
         result: Option<i32>,
     ) {
-        let op_code = match operator {
-            QuadOperator::Goto => 1,
-            QuadOperator::GotoV => 2,
-            QuadOperator::GotoF => 3,
-            QuadOperator::Assign => 4,
-            QuadOperator::Add => 5,
-            QuadOperator::Subtract => 6,
-            QuadOperator::Multiply => 7,
-            QuadOperator::Divide => 8,
-            QuadOperator::GreaterThan => 9,
-            QuadOperator::LessThan => 10,
-            QuadOperator::NotEqual => 11,
-            QuadOperator::Print => 12,
-            QuadOperator::Memory => 13,
-            QuadOperator::Param => 14,
-            QuadOperator::GoSub => 15,
-            QuadOperator::EndFunc => 16,
-            QuadOperator::EndProgram => 17,
-        };
-
+        let op_code = operator.code();
         let quad = Quadruplet::new(op_code, arg1, arg2, result);
         self.quadruplets.push(quad);
-        self.instruction_pointer = self.instruction_pointer + 1;
+        self.instruction_pointer += 1;
+    }
+
+    /// Gives the position of a statement to the quads created since the
+    /// last statement was marked.
+    pub fn mark_statement(&mut self, position: Option<usize>) {
+        for index in self.marked_quads..self.quadruplets.len() {
+            if let Some(quad) = self.quadruplets.get_mut(index) {
+                quad.position = position;
+            }
+        }
+        self.marked_quads = self.quadruplets.len();
     }
 
     pub fn fill_quad(&mut self, index: i32, result: i32) {
-        if let Some(quad) = self.quadruplets.get(index) {
+        if let Some(quad) = self.quadruplets.get_mut(index) {
             let quad_op_code = convert_quad_op_to_code(quad.operator);
             match quad_op_code {
-                QuadOperator::Goto => {
-                    self.quadruplets
-                        .set(index, Quadruplet::new(quad.operator, result, None, None));
+                QuadOperator::Goto | QuadOperator::Memory | QuadOperator::GoSub => {
+                    quad.arg1 = result;
                 }
-                QuadOperator::GotoF => {
-                    self.quadruplets.set(
-                        index,
-                        Quadruplet::new(quad.operator, quad.arg1, Some(result), None),
-                    );
+                QuadOperator::GotoF | QuadOperator::Param => {
+                    quad.arg2 = Some(result);
                 }
                 _ => {
                     panic!("Invalid quad operator for fill_quad");
@@ -113,528 +475,4 @@ impl ProgramManager {
             panic!("Quadruplet not found at index {}", index);
         }
     }
-
-    pub fn run_program(&mut self) {
-        if self.quadruplets.len() == 0 {
-            panic!("There's no quadruplets")
-        }
-
-        self.instruction_pointer = 0;
-        while self.instruction_pointer < self.quadruplets.len() as i32 {
-            let quad = self.quadruplets.get(self.instruction_pointer).unwrap();
-            let op_code: QuadOperator = convert_quad_op_to_code(quad.operator);
-
-            match op_code {
-                QuadOperator::Add => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 + arg2;
-                        self.value_table.set_int(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 + arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 as f64 + arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 + arg2 as f64;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::Subtract => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 - arg2;
-                        self.value_table.set_int(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 - arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 as f64 - arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 - arg2 as f64;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::Multiply => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 * arg2;
-                        self.value_table.set_int(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 * arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 as f64 * arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 * arg2 as f64;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::Divide => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 / arg2;
-                        self.value_table.set_int(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 / arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 as f64 / arg2;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 / arg2 as f64;
-                        self.value_table.set_float(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::GreaterThan => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 > arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 > arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 as f64 > arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 > arg2 as f64;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::LessThan => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 < arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 < arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = (arg1 as f64) < arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 < arg2 as f64;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::NotEqual => {
-                    let arg1_type = self.value_table.get_var_type(quad.arg1);
-                    let arg2_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if arg1_type == Type::Int && arg2_type == Type::Int {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 != arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Float {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 != arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Int && arg2_type == Type::Float {
-                        let arg1 = self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = (arg1 as f64) < arg2;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    } else if arg1_type == Type::Float && arg2_type == Type::Int {
-                        let arg1 = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        let arg2 = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        let result = arg1 != arg2 as f64;
-                        self.value_table.set_bool(
-                            quad.result.unwrap(),
-                            result,
-                            self.memory_stack.top_mut(),
-                        );
-                    }
-                }
-                QuadOperator::GotoF => {
-                    let var_value = self
-                        .value_table
-                        .get_bool(quad.arg1, self.memory_stack.top());
-                    if var_value == false {
-                        self.instruction_pointer = quad.arg2.unwrap();
-                        continue;
-                    }
-                }
-                QuadOperator::Goto => {
-                    self.instruction_pointer = quad.arg1;
-                    continue;
-                }
-                QuadOperator::Assign => {
-                    let var_type = self.value_table.get_var_type(quad.arg1);
-                    let result_type = self.value_table.get_var_type(quad.arg2.unwrap());
-
-                    if var_type == Type::Int && result_type == Type::Int {
-                        let value = self
-                            .value_table
-                            .get_int(quad.arg2.unwrap(), self.memory_stack.top());
-                        self.value_table
-                            .set_int(quad.arg1, value, self.memory_stack.top_mut());
-                    } else if var_type == Type::Float && result_type == Type::Float {
-                        let value = self
-                            .value_table
-                            .get_float(quad.arg2.unwrap(), self.memory_stack.top());
-                        self.value_table
-                            .set_float(quad.arg1, value, self.memory_stack.top_mut());
-                    }
-                }
-                QuadOperator::Print => {
-                    let var_type = self.value_table.get_var_type(quad.arg1);
-
-                    match var_type {
-                        Type::Int => {
-                            let value =
-                                self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                            println!("{}", value);
-                        }
-                        Type::Float => {
-                            let value = self
-                                .value_table
-                                .get_float(quad.arg1, self.memory_stack.top());
-                            println!("{}", value);
-                        }
-                        Type::String => {
-                            let value = self.value_table.get_string(quad.arg1);
-                            println!("{}", value);
-                        }
-                        _ => {
-                            panic!(
-                                "Invalid Type for print statement at quad {}",
-                                self.instruction_pointer
-                            );
-                        }
-                    }
-                }
-                QuadOperator::Memory => {
-                    let function_name = self.function_ids.get(&quad.arg1).unwrap();
-                    let function_info =
-                        &self.tabla_funciones.get(function_name).unwrap().vars_amount;
-                    let local_int_amount = function_info[0][0];
-                    let temp_int_amount = function_info[0][1];
-                    let local_float_amount = function_info[1][0];
-                    let temp_float_amount = function_info[1][1];
-                    let temp_bool_amount = function_info[2][0];
-
-                    let curr_memory = Memory {
-                        values: vec![
-                            vec![
-                                vec![Value::Int(0); local_int_amount as usize],
-                                vec![Value::Int(0); temp_int_amount as usize],
-                            ],
-                            vec![
-                                vec![Value::Float(0.0); local_float_amount as usize],
-                                vec![Value::Float(0.0); temp_float_amount as usize],
-                            ],
-                            vec![vec![Value::Bool(false); temp_bool_amount as usize]],
-                        ],
-                    };
-                    self.upcoming_function = Some(curr_memory);
-                }
-                QuadOperator::Param => {
-                    if quad.arg2.unwrap() >= 5000 {
-                        let var_value = self
-                            .value_table
-                            .get_float(quad.arg1, self.memory_stack.top());
-                        self.value_table.set_float(
-                            quad.arg2.unwrap(),
-                            var_value,
-                            self.upcoming_function.as_mut(),
-                        );
-                    } else {
-                        let var_value =
-                            self.value_table.get_int(quad.arg1, self.memory_stack.top());
-                        self.value_table.set_int(
-                            quad.arg2.unwrap(),
-                            var_value,
-                            self.upcoming_function.as_mut(),
-                        );
-                    }
-                }
-                QuadOperator::GoSub => {
-                    self.memory_stack
-                        .push(self.upcoming_function.clone().unwrap());
-                    self.upcoming_function = None;
-
-                    let function_name = self.function_ids.get(&quad.arg1).unwrap();
-                    let function_start_address = &self
-                        .tabla_funciones
-                        .get(function_name)
-                        .unwrap()
-                        .start_address;
-                    self.position_before_fcall
-                        .push(self.instruction_pointer + 1);
-                    self.instruction_pointer = *function_start_address;
-                    continue;
-                }
-                QuadOperator::EndFunc => {
-                    self.memory_stack.pop();
-                    self.instruction_pointer = self.position_before_fcall.pop().unwrap();
-                    continue;
-                }
-                QuadOperator::EndProgram => {
-                    println!("System exited with a value 0");
-                }
-                _ => {
-                    panic!("Invalid quad operator");
-                }
-            }
-            self.instruction_pointer += 1
-        }
-    }
 }
@@ -8,28 +8,14 @@ pub struct Quadruplet {
     pub arg1: i32,
     pub arg2: Option<i32>,
     pub result: Option<i32>,
+    /// Byte offset in the source of the statement that produced the quad.
+    pub position: Option<usize>,
 }
 
 impl Display for Quadruplet {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         let op_str = match self.operator {
-            1 => "GOTO",
-            2 => "GOTOV",
-            3 => "GOTOF",
-            4 => "ASSIGN",
-            5 => "SUM",
-            6 => "MINUS",
-            7 => "TIMES",
-            8 => "DIV",
-            9 => "GT",
-            10 => "LT",
-            11 => "NE",
-            12 => "PRINT",
-            13 => "MEMORY",
-            14 => "PARAM",
-            15 => "GOSUB",
-            16 => "ENDFUNC",
-            17 => "ENDPROGRAM",
+            1..=18 => self.op().name(),
             _ => "INVALID QUAD OP",
         };
 
@@ -51,6 +37,7 @@ impl Display for Quadruplet {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum QuadOperator {
     Goto,
     GotoV,
@@ -69,6 +56,7 @@ pub enum QuadOperator {
     Param,
     EndFunc,
     EndProgram,
+    CallBuiltin,
 }
 
 /*
@@ -87,6 +75,94 @@ pub enum QuadOperator {
 13 - end_program
 */
 
+impl QuadOperator {
+    /// Arithmetic and relational operators, which read two operands and
+    /// write a result.
+    pub fn is_binary(&self) -> bool {
+        matches!(
+            self,
+            QuadOperator::Add
+                | QuadOperator::Subtract
+                | QuadOperator::Multiply
+                | QuadOperator::Divide
+                | QuadOperator::GreaterThan
+                | QuadOperator::LessThan
+                | QuadOperator::NotEqual
+        )
+    }
+
+    pub const ALL: [QuadOperator; 18] = [
+        QuadOperator::Goto,
+        QuadOperator::GotoV,
+        QuadOperator::GotoF,
+        QuadOperator::Assign,
+        QuadOperator::Add,
+        QuadOperator::Subtract,
+        QuadOperator::Multiply,
+        QuadOperator::Divide,
+        QuadOperator::GreaterThan,
+        QuadOperator::LessThan,
+        QuadOperator::NotEqual,
+        QuadOperator::Print,
+        QuadOperator::Memory,
+        QuadOperator::Param,
+        QuadOperator::GoSub,
+        QuadOperator::EndFunc,
+        QuadOperator::EndProgram,
+        QuadOperator::CallBuiltin,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuadOperator::Goto => "GOTO",
+            QuadOperator::GotoV => "GOTOV",
+            QuadOperator::GotoF => "GOTOF",
+            QuadOperator::Assign => "ASSIGN",
+            QuadOperator::Add => "SUM",
+            QuadOperator::Subtract => "MINUS",
+            QuadOperator::Multiply => "TIMES",
+            QuadOperator::Divide => "DIV",
+            QuadOperator::GreaterThan => "GT",
+            QuadOperator::LessThan => "LT",
+            QuadOperator::NotEqual => "NE",
+            QuadOperator::Print => "PRINT",
+            QuadOperator::Memory => "MEMORY",
+            QuadOperator::Param => "PARAM",
+            QuadOperator::GoSub => "GOSUB",
+            QuadOperator::EndFunc => "ENDFUNC",
+            QuadOperator::EndProgram => "ENDPROGRAM",
+            QuadOperator::CallBuiltin => "CALLBUILTIN",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<QuadOperator> {
+        QuadOperator::ALL.into_iter().find(|op| op.name() == name)
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            QuadOperator::Goto => 1,
+            QuadOperator::GotoV => 2,
+            QuadOperator::GotoF => 3,
+            QuadOperator::Assign => 4,
+            QuadOperator::Add => 5,
+            QuadOperator::Subtract => 6,
+            QuadOperator::Multiply => 7,
+            QuadOperator::Divide => 8,
+            QuadOperator::GreaterThan => 9,
+            QuadOperator::LessThan => 10,
+            QuadOperator::NotEqual => 11,
+            QuadOperator::Print => 12,
+            QuadOperator::Memory => 13,
+            QuadOperator::Param => 14,
+            QuadOperator::GoSub => 15,
+            QuadOperator::EndFunc => 16,
+            QuadOperator::EndProgram => 17,
+            QuadOperator::CallBuiltin => 18,
+        }
+    }
+}
+
 impl Quadruplet {
     pub fn new(operator: i32, arg1: i32, arg2: Option<i32>, result: Option<i32>) -> Self {
         Quadruplet {
@@ -94,15 +170,95 @@ impl Quadruplet {
             arg1,
             arg2,
             result,
+            position: None,
+        }
+    }
+
+    pub fn op(&self) -> QuadOperator {
+        convert_quad_op_to_code(self.operator)
+    }
+
+    /// Addresses this quad reads from the running function's memory.
+    pub fn reads(&self) -> Vec<i32> {
+        match self.op() {
+            QuadOperator::GotoV
+            | QuadOperator::GotoF
+            | QuadOperator::Print
+            | QuadOperator::Param => {
+                vec![self.arg1]
+            }
+            QuadOperator::Assign => self.arg2.into_iter().collect(),
+            op if op.is_binary() => {
+                let mut reads = vec![self.arg1];
+                reads.extend(self.arg2);
+                reads
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Address this quad writes in the running function's memory. `PARAM`
+    /// writes into the callee's memory, so it isn't included.
+    pub fn writes(&self) -> Option<i32> {
+        match self.op() {
+            QuadOperator::Assign => Some(self.arg1),
+            QuadOperator::CallBuiltin => self.result,
+            op if op.is_binary() => self.result,
+            _ => None,
+        }
+    }
+
+    pub fn jump_target(&self) -> Option<i32> {
+        match self.op() {
+            QuadOperator::Goto => Some(self.arg1),
+            QuadOperator::GotoV | QuadOperator::GotoF => self.arg2,
+            _ => None,
+        }
+    }
+
+    /// Rewrites every address returned by `reads`.
+    pub fn map_reads(&mut self, mut f: impl FnMut(i32) -> i32) {
+        match self.op() {
+            QuadOperator::GotoV
+            | QuadOperator::GotoF
+            | QuadOperator::Print
+            | QuadOperator::Param => {
+                self.arg1 = f(self.arg1);
+            }
+            QuadOperator::Assign => {
+                self.arg2 = self.arg2.map(&mut f);
+            }
+            op if op.is_binary() => {
+                self.arg1 = f(self.arg1);
+                self.arg2 = self.arg2.map(&mut f);
+            }
+            _ => {}
+        }
+    }
+
+    /// Rewrites every address returned by `reads` and `writes`.
+    pub fn map_addresses(&mut self, mut f: impl FnMut(i32) -> i32) {
+        self.map_reads(&mut f);
+        match self.op() {
+            QuadOperator::Assign => self.arg1 = f(self.arg1),
+            QuadOperator::CallBuiltin => self.result = self.result.map(&mut f),
+            op if op.is_binary() => self.result = self.result.map(&mut f),
+            _ => {}
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct QuadrupletList {
     quadruplets: Queue<Quadruplet>,
 }
 
+impl Default for QuadrupletList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl QuadrupletList {
     pub fn new() -> Self {
         QuadrupletList {
@@ -119,6 +275,10 @@ impl QuadrupletList {
         self.quadruplets.len() as i32
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.quadruplets.is_empty()
+    }
+
     pub fn get(&self, index: i32) -> Option<&Quadruplet> {
         self.quadruplets.get(index as usize)
     }
@@ -127,6 +287,14 @@ impl QuadrupletList {
         self.quadruplets.set(index as usize, value);
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Quadruplet> {
+        self.quadruplets.iter()
+    }
+
+    pub fn get_mut(&mut self, index: i32) -> Option<&mut Quadruplet> {
+        self.quadruplets.get_mut(index as usize)
+    }
+
     pub fn print_elements(&self) {
         self.quadruplets.print_elements();
     }
@@ -145,6 +313,19 @@ pub fn convert_semantic_op_to_quad_op(op: Operator) -> QuadOperator {
     }
 }
 
+pub fn convert_quad_op_to_semantic_op(op: QuadOperator) -> Option<Operator> {
+    match op {
+        QuadOperator::Add => Some(Operator::Add),
+        QuadOperator::Subtract => Some(Operator::Subtract),
+        QuadOperator::Multiply => Some(Operator::Multiply),
+        QuadOperator::Divide => Some(Operator::Divide),
+        QuadOperator::GreaterThan => Some(Operator::GreaterThan),
+        QuadOperator::LessThan => Some(Operator::LessThan),
+        QuadOperator::NotEqual => Some(Operator::NotEqual),
+        _ => None,
+    }
+}
+
 pub fn convert_quad_op_to_code(op: i32) -> QuadOperator {
     match op {
         1 => QuadOperator::Goto,
@@ -164,6 +345,7 @@ pub fn convert_quad_op_to_code(op: i32) -> QuadOperator {
         15 => QuadOperator::GoSub,
         16 => QuadOperator::EndFunc,
         17 => QuadOperator::EndProgram,
+        18 => QuadOperator::CallBuiltin,
         _ => panic!("Invalid operator"),
     }
 }
@@ -1,3 +1,5 @@
+use crate::compiler::value_table::Value;
+use std::cmp::Ordering::{self, Equal, Greater, Less};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -21,8 +23,18 @@ pub enum Operator {
     NotEqual,
 }
 
+/// Computes an operation on two values whose types the cube accepts.
+pub type Operation = fn(&Value, &Value) -> Result<Value, String>;
+
 pub struct CuboSemantico {
     pub res_operaciones: HashMap<(Type, Operator, Type), Type>,
+    operations: HashMap<(Type, Operator, Type), Operation>,
+}
+
+impl Default for CuboSemantico {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CuboSemantico {
@@ -103,7 +115,16 @@ impl CuboSemantico {
         res_operaciones.insert((Type::Float, Operator::Subtract, Type::Bool), Type::Error);
         res_operaciones.insert((Type::Float, Operator::Divide, Type::Bool), Type::Error);
 
-        Self { res_operaciones }
+        // Every valid entry of the cube gets the function that computes it
+        let operations = res_operaciones
+            .iter()
+            .filter_map(|(key, result)| Some((key.clone(), operation(key.1, result)?)))
+            .collect();
+
+        Self {
+            res_operaciones,
+            operations,
+        }
     }
 
     pub fn get_type(&self, t1: Type, op: Operator, t2: Type) -> Type {
@@ -112,4 +133,74 @@ impl CuboSemantico {
             None => Type::Error,
         }
     }
+
+    /// Applies `op` to two values through the operation of their types.
+    pub fn evaluate(&self, left: &Value, op: Operator, right: &Value) -> Result<Value, String> {
+        match self
+            .operations
+            .get(&(left.value_type(), op, right.value_type()))
+        {
+            Some(operation) => operation(left, right),
+            None => Err(format!(
+                "Invalid operation between {:?} and {:?}",
+                left.value_type(),
+                right.value_type()
+            )),
+        }
+    }
+}
+
+// The function that computes `op` when the cube says it gives `result`
+fn operation(op: Operator, result: &Type) -> Option<Operation> {
+    let operation: Operation = match (result, op) {
+        (Type::Int, Operator::Add) => |l, r| checked(ints(l, r), i64::checked_add),
+        (Type::Int, Operator::Subtract) => |l, r| checked(ints(l, r), i64::checked_sub),
+        (Type::Int, Operator::Multiply) => |l, r| checked(ints(l, r), i64::checked_mul),
+        (Type::Int, Operator::Divide) => |l, r| match ints(l, r) {
+            (_, 0) => Err("Division by zero".to_string()),
+            values => checked(values, i64::checked_div),
+        },
+        (Type::Float, Operator::Add) => |l, r| Ok(Value::Float(as_float(l) + as_float(r))),
+        (Type::Float, Operator::Subtract) => |l, r| Ok(Value::Float(as_float(l) - as_float(r))),
+        (Type::Float, Operator::Multiply) => |l, r| Ok(Value::Float(as_float(l) * as_float(r))),
+        (Type::Float, Operator::Divide) => |l, r| Ok(Value::Float(as_float(l) / as_float(r))),
+        (Type::Bool, Operator::LessThan) => |l, r| Ok(Value::Bool(compare(l, r) == Some(Less))),
+        (Type::Bool, Operator::GreaterThan) => {
+            |l, r| Ok(Value::Bool(compare(l, r) == Some(Greater)))
+        }
+        (Type::Bool, Operator::Equal) => |l, r| Ok(Value::Bool(compare(l, r) == Some(Equal))),
+        (Type::Bool, Operator::NotEqual) => |l, r| Ok(Value::Bool(compare(l, r) != Some(Equal))),
+        _ => return None,
+    };
+    Some(operation)
+}
+
+fn ints(left: &Value, right: &Value) -> (i64, i64) {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => (*l, *r),
+        _ => panic!("Integer operation between {:?} and {:?}", left, right),
+    }
+}
+
+fn checked((l, r): (i64, i64), op: fn(i64, i64) -> Option<i64>) -> Result<Value, String> {
+    op(l, r)
+        .map(Value::Int)
+        .ok_or_else(|| "Integer overflow".to_string())
+}
+
+fn as_float(value: &Value) -> f64 {
+    match value {
+        Value::Int(val) => *val as f64,
+        Value::Float(val) => *val,
+        Value::Bool(val) => *val as i64 as f64,
+    }
+}
+
+// Ints and bools are compared as they are, mixed values as floats
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => l.partial_cmp(r),
+        (Value::Bool(l), Value::Bool(r)) => l.partial_cmp(r),
+        _ => as_float(left).partial_cmp(&as_float(right)),
+    }
 }
@@ -36,5 +36,17 @@ pub struct FunctionInfo {
     pub start_address: i32,
 }
 
+/// A call site whose `MEMORY`, `PARAM` and `GOSUB` quads are filled once the
+/// callee is known, so functions can be called before they are declared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    pub position: usize,
+    pub operands_start: usize,
+    pub memory_quad: i32,
+    pub param_quads: Vec<i32>,
+    pub gosub_quad: i32,
+}
+
 pub type VariableTable = HashMap<String, VariableInfo>;
 pub type FunctionTable = HashMap<String, FunctionInfo>;
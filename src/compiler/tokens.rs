@@ -34,6 +34,8 @@ pub enum Token {
     Print,
     #[token("var")]
     Var,
+    #[token("const")]
+    Const,
     #[token("void")]
     Void,
     #[token("while")]
@@ -92,7 +94,7 @@ pub enum Token {
     RBracket,
     #[token(".")]
     Dot,
-    #[regex(r"[ \t\n\f]+", logos::skip)]
+    #[regex(r"[ \t\r\n\f]+", logos::skip)]
     Whitespace,
     #[error]
     Error,
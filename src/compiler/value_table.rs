@@ -1,15 +1,7 @@
+use crate::compiler::memory_layout::{MemoryLayout, Scope, Segment};
 use crate::compiler::semantic_cube::Type;
 use std::collections::HashMap;
-
-pub const START_INT_VALUES: i32 = 1000;
-pub const START_FLOAT_VALUES: i32 = 4000;
-const START_BOOL_VALUES: i32 = 8000;
-const START_CONST_INT_VALUES: i32 = 9000;
-const START_CONST_FLOAT_VALUES: i32 = 10000;
-const START_CONST_STRING_VALUES: i32 = 11000;
-
-pub const LOCAL_VALUES: i32 = 1000;
-const TEMP_VALUES: i32 = 2000;
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -18,333 +10,497 @@ pub enum Value {
     Bool(bool),
 }
 
+impl Value {
+    pub fn value_type(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+        }
+    }
+}
+
+// Floats keep their decimal point, the way the disassembler shows literals
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
-enum ConstValue {
+pub enum ConstValue {
     Int(i64),
     Float(f64),
     String(String),
 }
 
+// Floats are keyed by their bit pattern so equal literals share an address
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstKey {
+    Int(i64),
+    Float(u64),
+    String(String),
+}
+
+impl ConstKey {
+    fn new(value: &ConstValue) -> Self {
+        match value {
+            ConstValue::Int(val) => ConstKey::Int(*val),
+            ConstValue::Float(val) => ConstKey::Float(val.to_bits()),
+            ConstValue::String(val) => ConstKey::String(val.clone()),
+        }
+    }
+}
+
+/// Literal values of the program, stored once per distinct value and type.
 #[derive(Debug, Clone, PartialEq)]
+pub struct ConstantPool {
+    segments: Vec<Segment>,
+    values: Vec<Vec<ConstValue>>,
+    addresses: HashMap<ConstKey, i32>,
+}
+
+impl Default for ConstantPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstantPool {
+    pub fn new() -> Self {
+        ConstantPool::with_layout(&MemoryLayout::default())
+    }
+
+    pub fn with_layout(layout: &MemoryLayout) -> Self {
+        let segments = [Type::Int, Type::Float, Type::String]
+            .iter()
+            .map(|var_type| match layout.segment(var_type, Scope::Constant) {
+                Some(segment) => segment.clone(),
+                None => Segment::new(var_type.clone(), Scope::Constant, 0, 0),
+            })
+            .collect();
+
+        ConstantPool {
+            segments,
+            values: vec![vec![], vec![], vec![]],
+            addresses: HashMap::new(),
+        }
+    }
+
+    fn index(value: &ConstValue) -> usize {
+        match value {
+            ConstValue::Int(_) => 0,
+            ConstValue::Float(_) => 1,
+            ConstValue::String(_) => 2,
+        }
+    }
+
+    pub fn insert(&mut self, value: ConstValue) -> Result<i32, String> {
+        let key = ConstKey::new(&value);
+        if let Some(address) = self.addresses.get(&key) {
+            return Ok(*address);
+        }
+
+        let index = Self::index(&value);
+        let segment = &self.segments[index];
+        let position = self.values[index].len() as i32;
+        if position >= segment.capacity {
+            return Err(format!(
+                "Out of memory for const {} values: the segment holds {} addresses",
+                type_name(&segment.var_type),
+                segment.capacity
+            ));
+        }
+
+        let address = segment.base + position;
+        self.values[index].push(value);
+        self.addresses.insert(key, address);
+        Ok(address)
+    }
+
+    pub fn address_of(&self, value: &ConstValue) -> Option<i32> {
+        self.addresses.get(&ConstKey::new(value)).copied()
+    }
+
+    pub fn get(&self, address: i32) -> Option<&ConstValue> {
+        let index = self
+            .segments
+            .iter()
+            .position(|segment| segment.contains(address))?;
+        self.values[index].get(self.segments[index].offset(address))
+    }
+
+    /// Every constant with its address, ordered by segment.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &ConstValue)> {
+        self.values
+            .iter()
+            .zip(&self.segments)
+            .flat_map(|(values, segment)| {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(position, value)| (segment.base + position as i32, value))
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.iter().map(|segment| segment.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// One constant per line as `<type> <address> <value>`. Strings take the
+    /// rest of the line, since string literals can't contain newlines.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (address, value) in self.iter() {
+            let line = match value {
+                ConstValue::Int(val) => format!("int {} {}", address, val),
+                ConstValue::Float(val) => format!("float {} {:?}", address, val),
+                ConstValue::String(val) => format!("string {} {}", address, val),
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn from_text(text: &str, layout: &MemoryLayout) -> Result<Self, String> {
+        let mut pool = ConstantPool::with_layout(layout);
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parts = line.splitn(3, ' ');
+            let (kind, address, value) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(kind), Some(address), Some(value)) => (kind, address, value),
+                _ => return Err(format!("Invalid constant entry: {}", line)),
+            };
+            let value = match kind {
+                "int" => ConstValue::Int(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid int: {}", value))?,
+                ),
+                "float" => ConstValue::Float(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid float: {}", value))?,
+                ),
+                "string" => ConstValue::String(value.to_string()),
+                _ => return Err(format!("Invalid constant type: {}", kind)),
+            };
+            let expected: i32 = address
+                .parse()
+                .map_err(|_| format!("Invalid address: {}", address))?;
+            if pool.insert(value)? != expected {
+                return Err(format!(
+                    "Constant at {} is out of order or repeated",
+                    expected
+                ));
+            }
+        }
+        Ok(pool)
+    }
+}
+
+pub fn type_name(var_type: &Type) -> &'static str {
+    match var_type {
+        Type::Int => "int",
+        Type::Float => "float",
+        Type::Bool => "bool",
+        Type::String => "string",
+        Type::Error => "error",
+    }
+}
+
+fn type_index(var_type: &Type) -> usize {
+    match var_type {
+        Type::Int => 0,
+        Type::Float => 1,
+        Type::Bool => 2,
+        _ => panic!("There's no memory for {:?} values", var_type),
+    }
+}
+
+// Globals and locals share the first slot of a memory, temps use the second
+fn lifetime_index(scope: Scope) -> usize {
+    match scope {
+        Scope::Temp => 1,
+        _ => 0,
+    }
+}
+
+fn default_value(var_type: &Type) -> Value {
+    match var_type {
+        Type::Int => Value::Int(0),
+        Type::Float => Value::Float(0.0),
+        Type::Bool => Value::Bool(false),
+        _ => panic!("There's no memory for {:?} values", var_type),
+    }
+}
 
+/// Values of one memory, indexed by type (int, float, bool) and then by
+/// lifetime (global or local, temp).
+#[derive(Debug, Clone, PartialEq)]
 pub struct Memory {
     pub values: Vec<Vec<Vec<Value>>>,
 }
 
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Memory {
     pub fn new() -> Self {
         Memory {
-            values: vec![vec![vec![], vec![]], vec![vec![], vec![]], vec![vec![]]],
+            values: vec![vec![vec![], vec![]]; 3],
+        }
+    }
+
+    /// Creates a memory from the `vars_amount` of a function.
+    pub fn with_sizes(sizes: &[Vec<i32>]) -> Self {
+        let mut memory = Memory::new();
+        for (var_type, type_sizes) in [Type::Int, Type::Float, Type::Bool].iter().zip(sizes) {
+            for (lifetime, size) in type_sizes.iter().enumerate() {
+                memory.values[type_index(var_type)][lifetime] =
+                    vec![default_value(var_type); *size as usize];
+            }
         }
+        memory
+    }
+
+    /// How many values the memory holds.
+    pub fn size(&self) -> usize {
+        self.values.iter().flatten().map(Vec::len).sum()
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ValueTable {
     pub counters: HashMap<String, i32>,
+    pub layout: MemoryLayout,
     var_values: Memory,
-    const_values: Vec<Vec<ConstValue>>,
+    pub constants: ConstantPool,
+}
+
+impl Default for ValueTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ValueTable {
     pub fn new() -> Self {
+        ValueTable::with_layout(MemoryLayout::default())
+    }
+
+    pub fn with_layout(layout: MemoryLayout) -> Self {
         ValueTable {
             counters: HashMap::new(),
+            constants: ConstantPool::with_layout(&layout),
+            layout,
             var_values: Memory::new(),
-            const_values: vec![vec![], vec![], vec![]],
         }
     }
 
-    pub fn insert_integer(&mut self, value: i64, scope: &str, memory: Option<&Memory>) -> i32 {
-        let key = format!("int_{}", scope);
-        let offset = match scope {
-            "global" => START_INT_VALUES,
-            "local" => START_INT_VALUES + LOCAL_VALUES,
-            "temp" => START_INT_VALUES + TEMP_VALUES,
-            _ => panic!("Invalid scope"),
-        };
+    fn counter_key(var_type: &Type, scope: Scope) -> String {
+        format!("{}_{}", type_name(var_type), scope)
+    }
 
-        let array_index = match scope {
-            "global" => 0,
-            "local" => 0,
-            "temp" => 1,
-            _ => panic!("Invalid lifetime"),
+    /// Reserves the next address of the (type, scope) segment. Globals and
+    /// temps of main get their space in the global memory right away.
+    pub fn allocate(&mut self, var_type: Type, scope: Scope) -> Result<i32, String> {
+        let segment = match self.layout.segment(&var_type, scope) {
+            Some(segment) => segment,
+            None => {
+                return Err(format!(
+                    "There's no {} segment for {} values",
+                    scope,
+                    type_name(&var_type)
+                ))
+            }
         };
 
-        let address = match scope {
-            "global" => {
-                self.var_values.values[0][array_index].push(Value::Int(value));
-                *self.counters.get(&key).unwrap_or(&0) as i32 + offset
-            }
-            "temp" => {
-                if memory.is_none() {
-                    self.var_values.values[0][array_index].push(Value::Int(value));
-                }
-                *self.counters.get(&key).unwrap_or(&0) as i32 + offset
-            }
-            "local" => *self.counters.get(&key).unwrap_or(&0) as i32 + offset,
-            _ => {
-                panic!("Invalid scope");
+        let key = Self::counter_key(&var_type, scope);
+        let count = *self.counters.get(&key).unwrap_or(&0);
+        if count >= segment.capacity {
+            return Err(format!(
+                "Out of memory for {} {} values: the segment holds {} addresses",
+                scope,
+                type_name(&var_type),
+                segment.capacity
+            ));
+        }
+        let address = segment.base + count;
+
+        if scope == Scope::Global || scope == Scope::Temp {
+            let values = &mut self.var_values.values[type_index(&var_type)][lifetime_index(scope)];
+            if values.len() <= count as usize {
+                values.push(default_value(&var_type));
             }
-        };
+        }
 
-        *self.counters.entry(key.clone()).or_insert(0) += 1;
+        *self.counters.entry(key).or_insert(0) += 1;
+        Ok(address)
+    }
 
-        address
+    /// Replaces the global memory with a fresh one of the given sizes.
+    pub fn reset_memory(&mut self, sizes: &[Vec<i32>]) {
+        self.var_values = Memory::with_sizes(sizes);
     }
-    pub fn insert_float(&mut self, value: f64, scope: &str, memory: Option<&Memory>) -> i32 {
-        let key = format!("float_{}", scope);
-        let offset = match scope {
-            "global" => START_FLOAT_VALUES,
-            "local" => START_FLOAT_VALUES + LOCAL_VALUES,
-            "temp" => START_FLOAT_VALUES + TEMP_VALUES,
-            _ => panic!("Invalid scope"),
-        };
 
-        let array_index = match scope {
-            "global" => 0,
-            "local" => 0,
-            "temp" => 1,
-            _ => panic!("Invalid scope"),
-        };
+    /// The global memory, which also holds the temps of main.
+    pub fn memory(&self) -> &Memory {
+        &self.var_values
+    }
 
-        //TODO: Change this to store only the counters and create the space for main when finishing the program
+    pub fn set_memory(&mut self, memory: Memory) {
+        self.var_values = memory;
+    }
 
-        let address = match scope {
-            "global" => {
-                self.var_values.values[1][array_index].push(Value::Float(value));
-                *self.counters.get(&key).unwrap_or(&0) as i32 + offset
-            }
-            "temp" => {
-                if memory.is_none() {
-                    self.var_values.values[1][array_index].push(Value::Float(value));
-                }
-                *self.counters.get(&key).unwrap_or(&0) as i32 + offset
-            }
-            "local" => *self.counters.get(&key).unwrap_or(&0) as i32 + offset,
-            _ => {
-                panic!("Invalid scope");
+    pub fn clear_local_vars(&mut self) {
+        for segment in self.layout.segments() {
+            if segment.scope == Scope::Local || segment.scope == Scope::Temp {
+                let key = Self::counter_key(&segment.var_type, segment.scope);
+                *self.counters.entry(key).or_insert(0) = 0;
             }
-        };
-
-        *self.counters.entry(key.clone()).or_insert(0) += 1;
-
-        address
+        }
     }
 
-    pub fn clear_local_vars(&mut self) {
-        *self.counters.entry("int_local".to_string()).or_insert(0) = 0;
-        *self.counters.entry("int_temp".to_string()).or_insert(0) = 0;
-        *self.counters.entry("float_local".to_string()).or_insert(0) = 0;
-        *self.counters.entry("float_temp".to_string()).or_insert(0) = 0;
-        *self.counters.entry("bool_temp".to_string()).or_insert(0) = 0;
+    /// Values of `scope` and temps used so far, in the shape of
+    /// `FunctionInfo::vars_amount`.
+    pub fn memory_sizes(&self, scope: Scope) -> Vec<Vec<i32>> {
+        [Type::Int, Type::Float, Type::Bool]
+            .iter()
+            .map(|var_type| {
+                [scope, Scope::Temp]
+                    .iter()
+                    .map(|scope| {
+                        *self
+                            .counters
+                            .get(&Self::counter_key(var_type, *scope))
+                            .unwrap_or(&0)
+                    })
+                    .collect()
+            })
+            .collect()
     }
 
-    pub fn insert_bool(&mut self, value: bool, memory: Option<&Memory>) -> i32 {
-        let address = *self.counters.get("bool_temp").unwrap_or(&0) as i32 + START_BOOL_VALUES;
-        if memory.is_none() {
-            self.var_values.values[2][0].push(Value::Bool(value));
-        }
-        *self.counters.entry("bool_temp".to_string()).or_insert(0) += 1;
-        address
+    pub fn insert_cte_int(&mut self, value: i64) -> Result<i32, String> {
+        self.constants.insert(ConstValue::Int(value))
     }
 
-    pub fn insert_cte_int(&mut self, value: i64) -> i32 {
-        let address = self.const_values[0].len() as i32 + START_CONST_INT_VALUES;
-        self.const_values[0].push(ConstValue::Int(value));
-        address
+    pub fn insert_cte_float(&mut self, value: f64) -> Result<i32, String> {
+        self.constants.insert(ConstValue::Float(value))
     }
 
-    pub fn insert_cte_float(&mut self, value: f64) -> i32 {
-        let address = self.const_values[1].len() as i32 + START_CONST_FLOAT_VALUES;
-        self.const_values[1].push(ConstValue::Float(value));
-        address
+    pub fn insert_cte_string(&mut self, value: String) -> Result<i32, String> {
+        self.constants.insert(ConstValue::String(value))
     }
 
-    pub fn insert_cte_string(&mut self, value: String) -> i32 {
-        let address = self.const_values[2].len() as i32 + START_CONST_STRING_VALUES;
-        self.const_values[2].push(ConstValue::String(value));
-        address
+    pub fn is_constant(&self, address: i32) -> bool {
+        matches!(
+            self.layout.decode(address),
+            Some(Segment {
+                scope: Scope::Constant,
+                ..
+            })
+        )
     }
 
-    pub fn get_string(&mut self, address: i32) -> String {
-        let position = address - START_CONST_STRING_VALUES;
-        match &self.const_values[2][position as usize] {
-            ConstValue::String(value) => value.clone(),
+    pub fn get_string(&self, address: i32) -> String {
+        match self.constants.get(address) {
+            Some(ConstValue::String(value)) => value.clone(),
             _ => panic!("Invalid address for string value"),
         }
     }
 
-    pub fn get_int(&self, address: i32, memory: Option<&Memory>) -> i64 {
-        let mut array_index = 0;
-        let mut position = address - START_INT_VALUES;
-        let mut int_type = "var";
-
-        if address >= START_INT_VALUES + LOCAL_VALUES && address < START_INT_VALUES + TEMP_VALUES {
-            position = position - LOCAL_VALUES;
-            let value = match memory {
-                Some(mem) => match mem.values[0][0][position as usize] {
-                    Value::Int(val) => val,
-                    _ => panic!("Invalid local address: {}", address),
-                },
-                None => 0,
-            };
-            return value;
-        } else if address >= START_INT_VALUES + TEMP_VALUES && address < START_FLOAT_VALUES {
-            array_index = 1;
-            position = position - TEMP_VALUES;
-        } else if address >= START_CONST_INT_VALUES && address < START_CONST_FLOAT_VALUES {
-            int_type = "const";
-            position = address - START_CONST_INT_VALUES;
+    fn locate(&self, address: i32) -> (Scope, usize, usize, usize) {
+        match self.layout.decode(address) {
+            Some(segment) if segment.scope != Scope::Constant => (
+                segment.scope,
+                type_index(&segment.var_type),
+                lifetime_index(segment.scope),
+                segment.offset(address),
+            ),
+            Some(_) => panic!("Constant address {} has no memory", address),
+            None => panic!("Invalid address {}", address),
         }
+    }
 
-        match int_type {
-            "var" => match self.var_values.values[0][array_index][position as usize] {
-                Value::Int(value) => value,
-                _ => panic!("Invalid address"),
-            },
-            "const" => match self.const_values[0][position as usize] {
-                ConstValue::Int(value) => value,
-                _ => panic!("Invalid address"),
-            },
-            _ => panic!("Invalid address"),
+    /// Reads an address. Locals and temps live in the memory of the running
+    /// function, while main keeps its temps in the global memory.
+    pub fn get_value(&self, address: i32, memory: Option<&Memory>) -> Value {
+        if self.is_constant(address) {
+            return match self.constants.get(address) {
+                Some(ConstValue::Int(value)) => Value::Int(*value),
+                Some(ConstValue::Float(value)) => Value::Float(*value),
+                _ => panic!("Invalid constant address {}", address),
+            };
         }
+
+        let (scope, type_index, lifetime, offset) = self.locate(address);
+        let values = match (scope, memory) {
+            (Scope::Global, _) | (Scope::Temp, None) => &self.var_values,
+            (_, None) => panic!("Local address {} used outside of a function", address),
+            (_, Some(mem)) => mem,
+        };
+        values.values[type_index][lifetime][offset].clone()
     }
 
-    pub fn set_int(&mut self, address: i32, value: i64, memory: Option<&mut Memory>) {
-        let mut array_index = 0;
-        let mut position = address - START_INT_VALUES;
-        if address >= START_INT_VALUES + LOCAL_VALUES && address < START_INT_VALUES + TEMP_VALUES {
-            array_index = 0;
-            position = position - LOCAL_VALUES;
-        } else if address >= START_INT_VALUES + TEMP_VALUES {
-            array_index = 1;
-            position = position - TEMP_VALUES;
-        }
+    pub fn set_value(&mut self, address: i32, value: Value, memory: Option<&mut Memory>) {
+        let (scope, type_index, lifetime, offset) = self.locate(address);
+        let values = match (scope, memory) {
+            (Scope::Global, _) | (Scope::Temp, None) => &mut self.var_values,
+            (_, None) => panic!("Local address {} used outside of a function", address),
+            (_, Some(mem)) => mem,
+        };
+        values.values[type_index][lifetime][offset] = value;
+    }
 
-        match memory {
-            Some(val) => {
-                if address >= START_INT_VALUES + LOCAL_VALUES
-                    && address < START_INT_VALUES + TEMP_VALUES
-                {
-                    val.values[0][array_index][position as usize] = Value::Int(value);
-                } else {
-                    self.var_values.values[0][array_index][position as usize] = Value::Int(value);
-                }
-            }
-            None => {
-                self.var_values.values[0][array_index][position as usize] = Value::Int(value);
-            }
+    pub fn get_int(&self, address: i32, memory: Option<&Memory>) -> i64 {
+        match self.get_value(address, memory) {
+            Value::Int(value) => value,
+            _ => panic!("Invalid address for int value: {}", address),
         }
     }
 
-    pub fn get_float(&self, address: i32, memory: Option<&Memory>) -> f64 {
-        let mut array_index = 0;
-        let mut position = address - START_FLOAT_VALUES;
-        let mut float_type = "var";
-        if address >= START_FLOAT_VALUES + LOCAL_VALUES
-            && address < START_FLOAT_VALUES + TEMP_VALUES
-        {
-            array_index = 0;
-            position = position - LOCAL_VALUES;
-            let value = match memory {
-                Some(mem) => match mem.values[1][array_index][position as usize] {
-                    Value::Float(val) => val,
-                    _ => panic!("Invalid local address: {}", address),
-                },
-                None => 0 as f64,
-            };
-            return value;
-        } else if address >= START_FLOAT_VALUES + TEMP_VALUES && address < START_CONST_FLOAT_VALUES
-        {
-            array_index = 1;
-            position = position - TEMP_VALUES;
-        } else if address >= START_CONST_FLOAT_VALUES {
-            float_type = "const";
-            position = address - START_CONST_FLOAT_VALUES;
-        }
+    pub fn set_int(&mut self, address: i32, value: i64, memory: Option<&mut Memory>) {
+        self.set_value(address, Value::Int(value), memory);
+    }
 
-        match float_type {
-            "var" => match self.var_values.values[1][array_index][position as usize] {
-                Value::Float(value) => value,
-                _ => panic!("Invalid address"),
-            },
-            "const" => match self.const_values[1][position as usize] {
-                ConstValue::Float(value) => value,
-                _ => panic!("Invalid address"),
-            },
-            _ => panic!("Invalid address"),
+    pub fn get_float(&self, address: i32, memory: Option<&Memory>) -> f64 {
+        match self.get_value(address, memory) {
+            Value::Float(value) => value,
+            _ => panic!("Invalid address for float value: {}", address),
         }
     }
 
     pub fn set_float(&mut self, address: i32, value: f64, memory: Option<&mut Memory>) {
-        let mut array_index = 0;
-        let mut position = address - START_FLOAT_VALUES;
-        if address >= START_FLOAT_VALUES + LOCAL_VALUES
-            && address < START_FLOAT_VALUES + TEMP_VALUES
-        {
-            array_index = 0;
-            position = position - LOCAL_VALUES;
-        } else if address >= START_FLOAT_VALUES + TEMP_VALUES {
-            array_index = 1;
-            position = position - TEMP_VALUES;
-        }
-        match memory {
-            Some(val) => {
-                if address >= START_FLOAT_VALUES + LOCAL_VALUES
-                    && address < START_FLOAT_VALUES + TEMP_VALUES
-                {
-                    val.values[1][array_index][position as usize] = Value::Float(value);
-                } else {
-                    self.var_values.values[1][array_index][position as usize] = Value::Float(value);
-                }
-            }
-            None => {
-                self.var_values.values[1][array_index][position as usize] = Value::Float(value);
-            }
-        }
+        self.set_value(address, Value::Float(value), memory);
     }
 
     pub fn get_bool(&self, address: i32, memory: Option<&Memory>) -> bool {
-        match memory {
-            Some(val) => match val.values[2][0][(address - START_BOOL_VALUES) as usize] {
-                Value::Bool(val) => val,
-                _ => panic!("Invalid address for bool in function memory"),
-            },
-            None => match self.var_values.values[2][0][(address - START_BOOL_VALUES) as usize] {
-                Value::Bool(value) => value,
-                _ => panic!("Invalid address for bool in global memory"),
-            },
+        match self.get_value(address, memory) {
+            Value::Bool(value) => value,
+            _ => panic!("Invalid address for bool value: {}", address),
         }
     }
 
     pub fn set_bool(&mut self, address: i32, value: bool, memory: Option<&mut Memory>) {
-        match memory {
-            Some(val) => {
-                val.values[2][0][(address - START_BOOL_VALUES) as usize] = Value::Bool(value);
-            }
-            None => {
-                self.var_values.values[2][0][(address - START_BOOL_VALUES) as usize] =
-                    Value::Bool(value);
-            }
-        }
+        self.set_value(address, Value::Bool(value), memory);
     }
 
     pub fn get_var_type(&self, address: i32) -> Type {
-        if address >= START_INT_VALUES && address < START_FLOAT_VALUES {
-            Type::Int
-        } else if address >= START_FLOAT_VALUES && address < START_BOOL_VALUES {
-            Type::Float
-        } else if address >= START_BOOL_VALUES && address < START_CONST_INT_VALUES {
-            Type::Bool
-        } else if address >= START_CONST_INT_VALUES && address < START_CONST_FLOAT_VALUES {
-            Type::Int
-        } else if address >= START_CONST_FLOAT_VALUES && address < START_CONST_STRING_VALUES {
-            Type::Float
-        } else if address >= START_CONST_STRING_VALUES {
-            Type::String
-        } else {
-            Type::Error
+        match self.layout.decode(address) {
+            Some(segment) => segment.var_type.clone(),
+            None => Type::Error,
         }
     }
 }
@@ -2,10 +2,13 @@ extern crate lalrpop_util;
 use lalrpop_util::lalrpop_mod;
 
 pub mod compiler;
+pub mod optimizer;
 pub mod utils;
+pub mod vm;
 
-lalrpop_mod!(pub grammar);
+lalrpop_mod!(#[allow(clippy::all)] pub grammar);
 
+pub use compiler::compiled_program::CompiledProgram;
 pub use compiler::lexer::Lexer;
 pub use compiler::program_manager::ProgramManager;
 pub use compiler::semantic_tables::FunctionTable;
@@ -13,3 +16,104 @@ pub use compiler::tokens::{LexicalError, Token};
 pub use grammar::ProgramParser;
 pub use std::collections::HashMap;
 pub use utils::stack::Stack;
+pub use vm::virtual_machine::VirtualMachine;
+
+use compiler::builtins::BuiltinRegistry;
+use compiler::diagnostics::Diagnostic;
+use compiler::memory_layout::MemoryLayout;
+use compiler::value_table::Value;
+use optimizer::temps::FrameReport;
+use std::io::Write;
+use vm::limits::Limits;
+use vm::runtime_error::RuntimeError;
+
+/// How `compile` turns source code into a program.
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    pub layout: MemoryLayout,
+    /// Builtins programs can call, with the host functions of the
+    /// application added through `BuiltinRegistry::register_host`.
+    pub builtins: BuiltinRegistry,
+    /// Runs every optimizer pass over the quads.
+    pub optimize: bool,
+}
+
+/// What a program left behind when it reached its end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutcome {
+    pub executed_quads: u64,
+    /// Values of the variables of `main`, sorted by name.
+    pub globals: Vec<(String, Value)>,
+}
+
+/// Parses and checks a program, returning every error found when there's
+/// any.
+pub fn compile(source: &str, options: &CompileOptions) -> Result<CompiledProgram, Vec<Diagnostic>> {
+    compile_with_reports(source, options).map(|(program, _)| program)
+}
+
+/// Like `compile`, also returning how much frame memory recycling temps
+/// saved in each function when optimizing.
+pub fn compile_with_reports(
+    source: &str,
+    options: &CompileOptions,
+) -> Result<(CompiledProgram, Vec<FrameReport>), Vec<Diagnostic>> {
+    let mut manager = ProgramManager::with_layout(options.layout.clone());
+    manager.builtins = options.builtins.clone();
+    if let Err(error) = ProgramParser::new().parse(&mut manager, Lexer::new(source)) {
+        manager.diagnostics.push(Diagnostic::from(error));
+    }
+    if !manager.diagnostics.is_empty() {
+        return Err(manager.diagnostics);
+    }
+
+    let reports = match options.optimize {
+        true => optimize(&mut manager),
+        false => vec![],
+    };
+    Ok((CompiledProgram::from_program(&manager), reports))
+}
+
+fn optimize(manager: &mut ProgramManager) -> Vec<FrameReport> {
+    optimizer::folding::fold_constants(
+        &mut manager.quadruplets,
+        &mut manager.tabla_funciones,
+        &mut manager.value_table,
+        &manager.cubo,
+    );
+    optimizer::cse::eliminate_common_subexpressions(
+        &mut manager.quadruplets,
+        &mut manager.tabla_funciones,
+        &manager.value_table.layout,
+    );
+    optimizer::licm::hoist_loop_invariants(
+        &mut manager.quadruplets,
+        &mut manager.tabla_funciones,
+        &manager.value_table.layout,
+    );
+    optimizer::dead_code::eliminate_dead_code(
+        &mut manager.quadruplets,
+        &mut manager.tabla_funciones,
+    );
+    optimizer::temps::recycle_temps(
+        &mut manager.quadruplets,
+        &mut manager.tabla_funciones,
+        &manager.value_table.layout,
+    )
+}
+
+/// Runs a program from the start on a new machine, writing what it prints to
+/// `io`.
+pub fn run(
+    program: &CompiledProgram,
+    io: &mut dyn Write,
+    limits: Limits,
+) -> Result<RunOutcome, RuntimeError> {
+    let mut machine = VirtualMachine::with_limits(program, limits);
+    machine.set_output(io);
+    machine.run()?;
+    Ok(RunOutcome {
+        executed_quads: machine.executed_quads(),
+        globals: machine.globals(),
+    })
+}
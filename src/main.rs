@@ -1,31 +1,261 @@
-#![allow(warnings)]
+use pandora::vm::debugger::{Breakpoint, Debugger, Stop};
+use pandora::vm::limits::Limits;
+use pandora::vm::profiler::Profiler;
+use pandora::vm::snapshot::Snapshot;
+use pandora::vm::tracer::{TraceFormat, Tracer};
+use pandora::{compiler, optimizer, CompileOptions, CompiledProgram, VirtualMachine};
 
-mod compiler;
-mod utils;
-
-use compiler::lexer::Lexer;
-use compiler::program_manager::ProgramManager;
-use compiler::semantic_tables::FunctionTable;
-use grammar::ProgramParser;
-use lalrpop_util::lalrpop_mod;
-use std::collections::HashMap;
-use utils::stack::Stack;
+// Value given after `flag` on the command line
+fn option(args: &[String], flag: &str) -> Option<String> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).cloned()
+}
 
-lalrpop_mod!(pub grammar);
+// `--max-quads`, `--max-depth`, `--max-memory` and `--timeout-ms` bound a run
+fn limits(args: &[String]) -> Limits {
+    let number = |flag: &str| {
+        option(args, flag).map(|value| {
+            value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("{} takes a number", flag))
+        })
+    };
+    Limits {
+        max_quads: number("--max-quads"),
+        max_call_depth: number("--max-depth").map(|depth| depth as usize),
+        max_frame_memory: number("--max-memory").map(|memory| memory as usize),
+        deadline: number("--timeout-ms").map(std::time::Duration::from_millis),
+    }
+}
 
-fn normalize(s: &str) -> String {
-    s.split_whitespace().collect::<Vec<_>>().join(" ")
+// A machine with the `limits` of the arguments, seeded by `--seed <n>` so
+// `rand_int` and `rand_float` draw the same numbers on every run
+fn machine<'a>(program: &'a CompiledProgram, args: &[String]) -> VirtualMachine<'a> {
+    let mut machine = VirtualMachine::with_limits(program, limits(args));
+    if let Some(seed) = option(args, "--seed") {
+        machine.set_seed(seed.parse().expect("--seed takes a number"));
+    }
+    machine
 }
 
 fn main() {
-    let mut source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
-    source = normalize(&source);
-    let lexer = Lexer::new(&source);
-    let parser = ProgramParser::new();
+    let args: Vec<String> = std::env::args().collect();
+
+    // `--load <file>` runs a compiled program, or a `.pdasm` assembly listing,
+    // without parsing it again
+    if let Some(path) = option(&args, "--load") {
+        let data = std::fs::read(&path).expect("Unable to read file");
+        let program = if path.ends_with(".pdasm") {
+            compiler::assembler::assemble(&String::from_utf8_lossy(&data))
+        } else {
+            CompiledProgram::load(&data)
+        };
+        match program {
+            Ok(program) => {
+                if let Err(error) = machine(&program, &args).run() {
+                    eprintln!("{}", error);
+                }
+            }
+            Err(message) => eprintln!("{}", message),
+        }
+        return;
+    }
+
+    // The program to compile comes first, before any flag. The lexer skips
+    // whitespace, so the source keeps its lines for the debugger
+    let path = match args.get(1) {
+        Some(path) if !path.starts_with("--") => path.as_str(),
+        _ => "tests/test1.pdra",
+    };
+    let source = std::fs::read_to_string(path).expect("Unable to read file");
+    let options = CompileOptions {
+        optimize: true,
+        ..CompileOptions::default()
+    };
+    let (program, reports) = match pandora::compile_with_reports(&source, &options) {
+        Ok(compiled) => compiled,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            return;
+        }
+    };
+    for report in reports {
+        println!("{}", report);
+    }
+    if args.iter().any(|arg| arg == "--disassemble") {
+        print!("{}", compiler::disassembler::disassemble(&program));
+    } else {
+        program.quadruplets.print_elements();
+    }
 
-    let mut program_manager = ProgramManager::new();
+    // `--emit <file>` and `--emit-binary <file>` write the compiled program
+    if let Some(path) = option(&args, "--emit") {
+        std::fs::write(path, program.to_text()).expect("Unable to write object file");
+    }
+    if let Some(path) = option(&args, "--emit-binary") {
+        std::fs::write(path, program.to_bytes()).expect("Unable to write object file");
+    }
+
+    // `--cfg <dir>` writes the control-flow graph of each function as <dir>/<function>.dot
+    if let Some(dir) = option(&args, "--cfg") {
+        std::fs::create_dir_all(&dir).expect("Unable to create the graphs directory");
+        let graphs = optimizer::cfg::build_graphs(&program.quadruplets, &program.functions);
+        for graph in graphs {
+            let path = std::path::Path::new(&dir).join(format!("{}.dot", graph.function));
+            std::fs::write(&path, graph.to_dot(&program.quadruplets))
+                .expect("Unable to write graph");
+        }
+    }
+    if args.iter().any(|arg| arg == "--debug") {
+        debug(&program, &source);
+        return;
+    }
+
+    // `--profile` prints where the program spent its time, and
+    // `--profile-folded <file>` writes the stacks for a flamegraph
+    if args.iter().any(|arg| arg == "--profile") || option(&args, "--profile-folded").is_some() {
+        match Profiler::with_source(&program, &source).run() {
+            Ok(profile) => {
+                print!("{}", profile.table());
+                if let Some(path) = option(&args, "--profile-folded") {
+                    std::fs::write(path, profile.folded()).expect("Unable to write profile");
+                }
+            }
+            Err(error) => eprintln!("{}", error),
+        }
+        return;
+    }
+
+    // `--trace <file>` writes every executed quad, as JSON Lines with
+    // `--trace-json`, and `--trace-only f,g` keeps the quads of some functions
+    if let Some(path) = option(&args, "--trace") {
+        let sink = std::fs::File::create(path).expect("Unable to create trace file");
+        let format = match args.iter().any(|arg| arg == "--trace-json") {
+            true => TraceFormat::JsonLines,
+            false => TraceFormat::Text,
+        };
+        let mut tracer = Tracer::new(&program, std::io::BufWriter::new(sink), format);
+        if let Some(functions) = option(&args, "--trace-only") {
+            tracer = tracer.with_filter(&functions.split(',').collect::<Vec<_>>());
+        }
+        if let Err(error) = tracer.run() {
+            eprintln!("{}", error);
+        }
+        return;
+    }
+
+    // `--resume <file>` continues a saved run, and `--snapshot <file>` saves
+    // the state where the run fails or, with `--snapshot-at N`, after N quads
+    let mut machine = machine(&program, &args);
+    if let Some(path) = option(&args, "--resume") {
+        let text = std::fs::read_to_string(path).expect("Unable to read snapshot");
+        if let Err(message) =
+            Snapshot::from_text(&text).and_then(|snapshot| machine.restore(&snapshot))
+        {
+            eprintln!("{}", message);
+            return;
+        }
+    }
+    let snapshot_at = option(&args, "--snapshot-at")
+        .map(|quads| quads.parse::<u64>().expect("--snapshot-at takes a number"));
+    let mut result = Ok(());
+    while !machine.is_finished() && snapshot_at.is_none_or(|quads| machine.executed_quads() < quads)
+    {
+        result = machine.step();
+        if result.is_err() {
+            break;
+        }
+    }
+    if let Err(error) = &result {
+        eprintln!("{}", error);
+    }
+    if let Some(path) = option(&args, "--snapshot") {
+        if result.is_err() || !machine.is_finished() {
+            std::fs::write(path, machine.snapshot().to_text()).expect("Unable to write snapshot");
+        }
+    }
+}
 
-    parser.parse(&mut program_manager, lexer);
-    program_manager.quadruplets.print_elements();
-    program_manager.run_program();
+// Reads debugger commands from stdin until the program ends
+fn debug(program: &CompiledProgram, source: &str) {
+    let mut debugger = Debugger::with_source(program, source);
+    debugger.record_history(true);
+    for command in std::io::stdin().lines() {
+        let command = command.expect("Unable to read command");
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let stop = match words.as_slice() {
+            ["break" | "delete", target @ ..] => {
+                let breakpoint = match target {
+                    ["line", line] => line.parse().map(Breakpoint::Line),
+                    [quad] => quad.parse().map(Breakpoint::Quad),
+                    _ => {
+                        println!("Usage: break <quad> | break line <line>");
+                        continue;
+                    }
+                };
+                let result = match (words[0], breakpoint) {
+                    ("break", Ok(breakpoint)) => debugger.add_breakpoint(breakpoint),
+                    (_, Ok(breakpoint)) => debugger.remove_breakpoint(breakpoint),
+                    (_, Err(error)) => Err(error.to_string()),
+                };
+                if let Err(message) = result {
+                    println!("{}", message);
+                }
+                continue;
+            }
+            ["print", name] => {
+                match debugger.variable(name) {
+                    Some(value) => println!("{} = {}", name, value),
+                    None => println!("There's no variable {}", name),
+                }
+                continue;
+            }
+            ["stack"] => {
+                for frame in debugger.call_stack() {
+                    println!("{} at quad {}", frame.function, frame.instruction_pointer);
+                }
+                continue;
+            }
+            // Going back only fails when there's nothing left to undo
+            ["back" | "last" | "rewind", rest @ ..] => {
+                let stop = match (words[0], rest) {
+                    ("back", []) => debugger.step_back(),
+                    ("last", [name]) => debugger.back_to_last_write(name),
+                    ("rewind", [quads]) => match quads.parse() {
+                        Ok(quads) => debugger.rewind(quads),
+                        Err(error) => Err(error.to_string()),
+                    },
+                    _ => Err("Usage: back | last <variable> | rewind <executed quads>".to_string()),
+                };
+                Ok(stop.unwrap_or_else(|message| {
+                    println!("{}", message);
+                    Stop::Paused(debugger.instruction_pointer())
+                }))
+            }
+            ["step"] => debugger.step_into(),
+            ["next"] => debugger.step_over(),
+            ["out"] => debugger.step_out(),
+            ["continue"] => debugger.resume(),
+            ["quit"] => return,
+            _ => {
+                println!(
+                    "Commands: break, delete, step, next, out, continue, back, last, rewind, print, stack, quit"
+                );
+                continue;
+            }
+        };
+        match stop {
+            Ok(Stop::Finished) => return,
+            Ok(_) => match debugger.line() {
+                Some(line) => println!("quad {} line {}", debugger.instruction_pointer(), line),
+                None => println!("quad {}", debugger.instruction_pointer()),
+            },
+            Err(error) => {
+                eprintln!("{}", error);
+                return;
+            }
+        }
+    }
 }
@@ -0,0 +1,155 @@
+use crate::compiler::quadruplets::{QuadOperator, QuadrupletList};
+use crate::compiler::semantic_tables::FunctionTable;
+use crate::optimizer::function_ranges;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Quads that always run together: only the first one is a jump target and
+/// only the last one can transfer control.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub id: usize,
+    pub quads: Range<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlFlowGraph {
+    pub function: String,
+    pub blocks: Vec<BasicBlock>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// Builds the graph of every function, ordered by start address.
+pub fn build_graphs(
+    quadruplets: &QuadrupletList,
+    functions: &FunctionTable,
+) -> Vec<ControlFlowGraph> {
+    function_ranges(quadruplets, functions)
+        .into_iter()
+        .map(|(name, range)| ControlFlowGraph::build(quadruplets, name, range))
+        .collect()
+}
+
+fn ends_block(op: QuadOperator) -> bool {
+    matches!(
+        op,
+        QuadOperator::Goto
+            | QuadOperator::GotoF
+            | QuadOperator::GotoV
+            | QuadOperator::GoSub
+            | QuadOperator::EndFunc
+            | QuadOperator::EndProgram
+    )
+}
+
+impl ControlFlowGraph {
+    pub fn build(quadruplets: &QuadrupletList, function: String, range: Range<i32>) -> Self {
+        let mut leaders = BTreeSet::from([range.start]);
+        for i in range.clone() {
+            let quad = quadruplets.get(i).unwrap();
+            if let Some(target) = quad.jump_target() {
+                if range.contains(&target) {
+                    leaders.insert(target);
+                }
+            }
+            if ends_block(quad.op()) && i + 1 < range.end {
+                leaders.insert(i + 1);
+            }
+        }
+
+        let starts: Vec<i32> = leaders.into_iter().collect();
+        let blocks: Vec<BasicBlock> = starts
+            .iter()
+            .enumerate()
+            .map(|(id, start)| BasicBlock {
+                id,
+                quads: *start..starts.get(id + 1).copied().unwrap_or(range.end),
+            })
+            .collect();
+
+        let mut graph = ControlFlowGraph {
+            function,
+            blocks,
+            edges: vec![],
+        };
+        for block in &graph.blocks {
+            let last = block.quads.end - 1;
+            let quad = quadruplets.get(last).unwrap();
+            let falls_through = !matches!(
+                quad.op(),
+                QuadOperator::Goto | QuadOperator::EndFunc | QuadOperator::EndProgram
+            );
+            let mut successors = vec![];
+            if falls_through {
+                successors.extend(graph.block_of(last + 1));
+            }
+            if let Some(target) = quad.jump_target() {
+                successors.extend(graph.block_of(target));
+            }
+            for successor in successors {
+                if !graph.edges.contains(&(block.id, successor)) {
+                    graph.edges.push((block.id, successor));
+                }
+            }
+        }
+        graph
+    }
+
+    /// Block holding the quad at `index`, if it belongs to this function.
+    pub fn block_of(&self, index: i32) -> Option<usize> {
+        self.blocks
+            .iter()
+            .find(|block| block.quads.contains(&index))
+            .map(|block| block.id)
+    }
+
+    pub fn successors(&self, block: usize) -> Vec<usize> {
+        self.edges
+            .iter()
+            .filter(|(from, _)| *from == block)
+            .map(|(_, to)| *to)
+            .collect()
+    }
+
+    pub fn predecessors(&self, block: usize) -> Vec<usize> {
+        self.edges
+            .iter()
+            .filter(|(_, to)| *to == block)
+            .map(|(from, _)| *from)
+            .collect()
+    }
+
+    /// Graphviz description of the graph, with the quads of each block as
+    /// its label.
+    pub fn to_dot(&self, quadruplets: &QuadrupletList) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph \"{}\" {{", escape(&self.function)).unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for block in &self.blocks {
+            let mut label = format!("B{}\\l", block.id);
+            for i in block.quads.clone() {
+                let quad = quadruplets.get(i).unwrap();
+                label.push_str(&escape(&format!("{} {}", i, quad)));
+                label.push_str("\\l");
+            }
+            writeln!(dot, "    B{} [label=\"{}\"];", block.id, label).unwrap();
+        }
+        for (from, to) in &self.edges {
+            let last = quadruplets.get(self.blocks[*from].quads.end - 1).unwrap();
+            let is_jump = last.jump_target().and_then(|target| self.block_of(target)) == Some(*to);
+            let label = match last.op() {
+                QuadOperator::GotoF if is_jump => " [label=\"false\"]",
+                QuadOperator::GotoV if is_jump => " [label=\"true\"]",
+                _ => "",
+            };
+            writeln!(dot, "    B{} -> B{}{};", from, to, label).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
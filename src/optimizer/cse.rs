@@ -0,0 +1,74 @@
+use crate::compiler::memory_layout::{MemoryLayout, Scope};
+use crate::compiler::quadruplets::{QuadOperator, QuadrupletList};
+use crate::compiler::semantic_tables::FunctionTable;
+use crate::optimizer::cfg::build_graphs;
+use crate::optimizer::{remove_quads, replace_reads};
+use std::collections::{HashMap, HashSet};
+
+type Expression = (QuadOperator, i32, Option<i32>);
+
+/// Local value numbering: inside each basic block, a quad that repeats an
+/// earlier (operator, arg1, arg2) is removed and its temp is replaced by the
+/// earlier one. An entry is forgotten once one of its operands is written.
+/// Expects every temp to be written once, so it has to run before
+/// `recycle_temps`. Returns how many quads were removed.
+pub fn eliminate_common_subexpressions(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+    layout: &MemoryLayout,
+) -> usize {
+    let mut removed = HashSet::new();
+
+    for graph in build_graphs(quadruplets, functions) {
+        let range = graph.blocks[0].quads.start..graph.blocks[graph.blocks.len() - 1].quads.end;
+
+        for block in &graph.blocks {
+            let mut available: HashMap<Expression, i32> = HashMap::new();
+            for i in block.quads.clone() {
+                let quad = quadruplets.get(i).unwrap().clone();
+                let expression = normalize(quad.op(), quad.arg1, quad.arg2);
+                let result = match quad.result {
+                    Some(result) if quad.op().is_binary() => Some(result),
+                    _ => None,
+                };
+
+                if let (Some(result), Some(earlier)) = (result, available.get(&expression)) {
+                    let is_temp = layout
+                        .decode(result)
+                        .is_some_and(|segment| segment.scope == Scope::Temp);
+                    if is_temp && replace_reads(quadruplets, range.clone(), i, result, *earlier) {
+                        removed.insert(i);
+                        continue;
+                    }
+                }
+
+                if let Some(written) = quad.writes() {
+                    available.retain(|(_, arg1, arg2), result| {
+                        *arg1 != written && *arg2 != Some(written) && *result != written
+                    });
+                }
+                // A quad like `t1 = t1 + 1` no longer holds its own expression
+                if let Some(result) = result {
+                    if result != quad.arg1 && Some(result) != quad.arg2 {
+                        available.insert(expression, result);
+                    }
+                }
+            }
+        }
+    }
+
+    remove_quads(quadruplets, functions, &removed);
+    removed.len()
+}
+
+// Orders the operands of commutative operators so `a * b` and `b * a` match
+fn normalize(op: QuadOperator, arg1: i32, arg2: Option<i32>) -> Expression {
+    match (op, arg2) {
+        (QuadOperator::Add | QuadOperator::Multiply | QuadOperator::NotEqual, Some(arg2))
+            if arg2 < arg1 =>
+        {
+            (op, arg2, Some(arg1))
+        }
+        _ => (op, arg1, arg2),
+    }
+}
@@ -0,0 +1,84 @@
+use crate::compiler::quadruplets::{QuadOperator, QuadrupletList};
+use crate::compiler::semantic_tables::FunctionTable;
+use crate::optimizer::remove_quads;
+use std::collections::HashSet;
+
+/// Retargets jumps that land on a `GOTO` to its final destination, then
+/// removes the quads that can't be reached from quad 0 or from the start of a
+/// function, along with every `GOTO` to the quad that follows it. Returns how
+/// many quads were removed.
+pub fn eliminate_dead_code(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+) -> usize {
+    collapse_jump_chains(quadruplets);
+
+    let reachable = reachable_quads(quadruplets, functions);
+    let mut removed: HashSet<i32> = (0..quadruplets.len())
+        .filter(|i| !reachable.contains(i))
+        .collect();
+
+    // Walking backwards lets a jump over other removed jumps go too
+    for i in (0..quadruplets.len()).rev() {
+        let quad = quadruplets.get(i).unwrap();
+        if removed.contains(&i) || quad.op() != QuadOperator::Goto || quad.arg1 <= i {
+            continue;
+        }
+        if (i + 1..quad.arg1).all(|j| removed.contains(&j)) {
+            removed.insert(i);
+        }
+    }
+
+    remove_quads(quadruplets, functions, &removed);
+    removed.len()
+}
+
+fn collapse_jump_chains(quadruplets: &mut QuadrupletList) {
+    for i in 0..quadruplets.len() {
+        let target = match quadruplets.get(i).unwrap().jump_target() {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let mut visited = HashSet::from([i]);
+        let mut destination = target;
+        while let Some(quad) = quadruplets.get(destination) {
+            if quad.op() != QuadOperator::Goto || !visited.insert(destination) {
+                break;
+            }
+            destination = quad.arg1;
+        }
+
+        let quad = quadruplets.get_mut(i).unwrap();
+        match quad.op() {
+            QuadOperator::Goto => quad.arg1 = destination,
+            _ => quad.arg2 = Some(destination),
+        }
+    }
+}
+
+fn reachable_quads(quadruplets: &QuadrupletList, functions: &FunctionTable) -> HashSet<i32> {
+    let mut reachable = HashSet::new();
+    let mut pending: Vec<i32> = functions
+        .values()
+        .map(|function| function.start_address)
+        .collect();
+    pending.push(0);
+
+    while let Some(i) = pending.pop() {
+        let quad = match quadruplets.get(i) {
+            Some(quad) if reachable.insert(i) => quad,
+            _ => continue,
+        };
+        match quad.op() {
+            QuadOperator::Goto => pending.push(quad.arg1),
+            QuadOperator::GotoF | QuadOperator::GotoV => {
+                pending.push(i + 1);
+                pending.extend(quad.arg2);
+            }
+            QuadOperator::EndFunc | QuadOperator::EndProgram => {}
+            _ => pending.push(i + 1),
+        }
+    }
+    reachable
+}
@@ -0,0 +1,95 @@
+use crate::compiler::memory_layout::Scope;
+use crate::compiler::quadruplets::{convert_quad_op_to_semantic_op, QuadOperator, QuadrupletList};
+use crate::compiler::semantic_cube::{CuboSemantico, Type};
+use crate::compiler::semantic_tables::FunctionTable;
+use crate::compiler::value_table::{ConstValue, Value, ValueTable};
+use crate::optimizer::{function_ranges, remove_quads, replace_reads};
+use std::collections::HashSet;
+
+/// Evaluates arithmetic on literals at compile time and simplifies the int
+/// identities `x * 1`, `x / 1`, `x + 0`, `x - 0` and `x * 0`. The reads of a
+/// folded temp are rewritten to the literal or operand that replaces it, and
+/// the quad is removed. Expects every temp to be written once, so it has to
+/// run before `recycle_temps`. Returns how many quads were removed.
+pub fn fold_constants(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+    value_table: &mut ValueTable,
+    cubo: &CuboSemantico,
+) -> usize {
+    let mut removed = HashSet::new();
+
+    for (_, range) in function_ranges(quadruplets, functions) {
+        for i in range.clone() {
+            let quad = quadruplets.get(i).unwrap().clone();
+            let result = match quad.result {
+                Some(result) if quad.op().is_binary() => result,
+                _ => continue,
+            };
+            let is_temp = value_table
+                .layout
+                .decode(result)
+                .is_some_and(|segment| segment.scope == Scope::Temp);
+            if !is_temp {
+                continue;
+            }
+
+            let replacement = match simplify(value_table, cubo, quad.op(), quad.arg1, quad.arg2) {
+                Some(replacement) => replacement,
+                None => continue,
+            };
+            if replace_reads(quadruplets, range.clone(), i, result, replacement) {
+                removed.insert(i);
+            }
+        }
+    }
+
+    remove_quads(quadruplets, functions, &removed);
+    removed.len()
+}
+
+// Address holding the result of the operation when it's known without running it
+fn simplify(
+    value_table: &mut ValueTable,
+    cubo: &CuboSemantico,
+    op: QuadOperator,
+    arg1: i32,
+    arg2: Option<i32>,
+) -> Option<i32> {
+    let operator = convert_quad_op_to_semantic_op(op)?;
+    let arg2 = arg2?;
+    let left = literal(value_table, arg1);
+    let right = literal(value_table, arg2);
+
+    if let (Some(left), Some(right)) = (&left, &right) {
+        return match cubo.evaluate(left, operator, right) {
+            Ok(Value::Int(value)) => value_table.constants.insert(ConstValue::Int(value)).ok(),
+            Ok(Value::Float(value)) => value_table.constants.insert(ConstValue::Float(value)).ok(),
+            // Division by zero and overflow are left for the runtime to report
+            _ => None,
+        };
+    }
+
+    if value_table.get_var_type(arg1) != Type::Int || value_table.get_var_type(arg2) != Type::Int {
+        return None;
+    }
+    let is = |value: &Option<Value>, expected: i64| *value == Some(Value::Int(expected));
+    match op {
+        QuadOperator::Multiply if is(&left, 0) || is(&right, 0) => {
+            value_table.constants.insert(ConstValue::Int(0)).ok()
+        }
+        QuadOperator::Multiply if is(&left, 1) => Some(arg2),
+        QuadOperator::Multiply | QuadOperator::Divide if is(&right, 1) => Some(arg1),
+        QuadOperator::Add if is(&left, 0) => Some(arg2),
+        QuadOperator::Add | QuadOperator::Subtract if is(&right, 0) => Some(arg1),
+        _ => None,
+    }
+}
+
+fn literal(value_table: &ValueTable, address: i32) -> Option<Value> {
+    match value_table.constants.get(address)? {
+        ConstValue::Int(value) => Some(Value::Int(*value)),
+        ConstValue::Float(value) => Some(Value::Float(*value)),
+        ConstValue::String(_) => None,
+    }
+}
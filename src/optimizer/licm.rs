@@ -0,0 +1,190 @@
+use crate::compiler::memory_layout::{MemoryLayout, Scope};
+use crate::compiler::quadruplets::{QuadOperator, QuadrupletList};
+use crate::compiler::semantic_cube::Type;
+use crate::compiler::semantic_tables::FunctionTable;
+use crate::optimizer::function_ranges;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Range, RangeInclusive};
+
+/// Loops of a range, found from the `GOTO` that jumps back to the condition
+/// of a `while`. Each loop spans from its header through its last back edge,
+/// and inner loops come first.
+pub fn find_loops(quadruplets: &QuadrupletList, range: Range<i32>) -> Vec<RangeInclusive<i32>> {
+    let mut back_edges: BTreeMap<i32, i32> = BTreeMap::new();
+    for i in range.clone() {
+        let quad = quadruplets.get(i).unwrap();
+        if quad.op() == QuadOperator::Goto && quad.arg1 <= i && range.contains(&quad.arg1) {
+            let back_edge = back_edges.entry(quad.arg1).or_insert(i);
+            *back_edge = (*back_edge).max(i);
+        }
+    }
+
+    let mut loops: Vec<RangeInclusive<i32>> = back_edges
+        .into_iter()
+        .map(|(header, back_edge)| header..=back_edge)
+        .collect();
+    loops.sort_by_key(|body| body.end() - body.start());
+    loops
+}
+
+/// Moves the arithmetic quads whose operands don't change inside a loop to a
+/// preheader that runs once before the loop's condition. Divisions stay in
+/// place, since running them ahead of time could fail in a loop that never
+/// iterates, and so does int arithmetic that could overflow, unless it's part
+/// of the condition and nothing before it there could fail. Expects every
+/// temp to be written once, so it has to run before `recycle_temps`. Returns
+/// how many quads were moved, counting a quad once for every loop it leaves.
+pub fn hoist_loop_invariants(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+    layout: &MemoryLayout,
+) -> usize {
+    let mut moved = 0;
+
+    // Moving quads renumbers the list, so loops are searched again after each
+    // change until no loop has anything left to hoist.
+    'search: loop {
+        for (_, range) in function_ranges(quadruplets, functions) {
+            for body in find_loops(quadruplets, range.clone()) {
+                let invariants = loop_invariants(quadruplets, range.clone(), &body, layout);
+                if !invariants.is_empty() {
+                    moved += invariants.len();
+                    move_to_preheader(quadruplets, functions, &body, &invariants);
+                    continue 'search;
+                }
+            }
+        }
+        return moved;
+    }
+}
+
+fn loop_invariants(
+    quadruplets: &QuadrupletList,
+    range: Range<i32>,
+    body: &RangeInclusive<i32>,
+    layout: &MemoryLayout,
+) -> Vec<i32> {
+    let mut writes: HashMap<i32, usize> = HashMap::new();
+    for i in range {
+        if let Some(address) = quadruplets.get(i).unwrap().writes() {
+            *writes.entry(address).or_insert(0) += 1;
+        }
+    }
+    let written_in_loop: HashSet<i32> = body
+        .clone()
+        .filter_map(|i| quadruplets.get(i).unwrap().writes())
+        .collect();
+    // A called function can change any global
+    let calls = body
+        .clone()
+        .any(|i| quadruplets.get(i).unwrap().op() == QuadOperator::GoSub);
+
+    // The condition runs whenever the loop is reached, up to the GOTOF that
+    // leaves the loop
+    let exit = body.clone().find(|i| {
+        let quad = quadruplets.get(*i).unwrap();
+        quad.op() == QuadOperator::GotoF && quad.arg2.is_some_and(|target| target > *body.end())
+    });
+
+    let scope = |address: i32| layout.decode(address).map(|segment| segment.scope);
+    let overflows = |address: i32| {
+        layout
+            .decode(address)
+            .is_some_and(|segment| segment.var_type == Type::Int)
+    };
+    let mut invariants = vec![];
+    let mut hoisted = HashSet::new();
+    // Whether every quad left in place so far runs in the condition without
+    // any chance of failing
+    let mut harmless = true;
+    for i in body.clone() {
+        let quad = quadruplets.get(i).unwrap();
+        let arithmetic = matches!(
+            quad.op(),
+            QuadOperator::Add | QuadOperator::Subtract | QuadOperator::Multiply
+        );
+        let can_fail = match quad.op() {
+            QuadOperator::GreaterThan | QuadOperator::LessThan | QuadOperator::NotEqual => false,
+            QuadOperator::Assign => false,
+            _ if arithmetic => quad.result.is_some_and(overflows),
+            _ => true,
+        };
+        let in_condition = exit.is_some_and(|exit| i < exit);
+        let result = match quad.result {
+            Some(result) if arithmetic && scope(result) == Some(Scope::Temp) => result,
+            _ => {
+                harmless &= !can_fail;
+                continue;
+            }
+        };
+
+        let is_invariant = |address: i32| match scope(address) {
+            Some(Scope::Constant) => true,
+            _ if hoisted.contains(&address) => true,
+            Some(Scope::Global) if calls => false,
+            _ => !written_in_loop.contains(&address),
+        };
+        let safe = !can_fail || (in_condition && harmless);
+        if safe && writes[&result] == 1 && quad.reads().into_iter().all(is_invariant) {
+            invariants.push(i);
+            hoisted.insert(result);
+        } else {
+            harmless &= !can_fail;
+        }
+    }
+    invariants
+}
+
+fn move_to_preheader(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+    body: &RangeInclusive<i32>,
+    invariants: &[i32],
+) {
+    let header = *body.start();
+    let invariants: HashSet<i32> = invariants.iter().copied().collect();
+
+    let mut order: Vec<i32> = (0..header).collect();
+    order.extend(body.clone().filter(|i| invariants.contains(i)));
+    order.extend(body.clone().filter(|i| !invariants.contains(i)));
+    order.extend(body.end() + 1..quadruplets.len());
+
+    // A jump to a hoisted quad lands on the next quad that stays in the loop
+    let mut new_positions = HashMap::new();
+    for (position, old) in order.iter().enumerate() {
+        new_positions.insert(*old, position as i32);
+    }
+    let landing = |target: i32| {
+        (target..=quadruplets.len())
+            .find(|i| !invariants.contains(i))
+            .map(|i| new_positions.get(&i).copied().unwrap_or(i))
+            .unwrap()
+    };
+    // Entering the loop from outside goes through the preheader first
+    let retarget = |source: Option<i32>, target: i32| {
+        let from_loop = source.is_some_and(|source| body.contains(&source));
+        if target == header && !from_loop {
+            header
+        } else {
+            landing(target)
+        }
+    };
+
+    let mut reordered = QuadrupletList::new();
+    for old in &order {
+        let mut quad = quadruplets.get(*old).unwrap().clone();
+        match quad.op() {
+            QuadOperator::Goto => quad.arg1 = retarget(Some(*old), quad.arg1),
+            QuadOperator::GotoF | QuadOperator::GotoV => {
+                quad.arg2 = quad.arg2.map(|target| retarget(Some(*old), target));
+            }
+            _ => {}
+        }
+        reordered.push(quad);
+    }
+
+    for function in functions.values_mut() {
+        function.start_address = retarget(None, function.start_address);
+    }
+    *quadruplets = reordered;
+}
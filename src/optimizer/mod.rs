@@ -0,0 +1,121 @@
+pub mod cfg;
+pub mod cse;
+pub mod dead_code;
+pub mod folding;
+pub mod licm;
+pub mod temps;
+
+use crate::compiler::quadruplets::{QuadOperator, QuadrupletList};
+use crate::compiler::semantic_tables::FunctionTable;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Quads of every function, from its start address through its `ENDFUNC`
+/// (or `ENDPROGRAM` for main), ordered by start address.
+pub fn function_ranges(
+    quadruplets: &QuadrupletList,
+    functions: &FunctionTable,
+) -> Vec<(String, Range<i32>)> {
+    let mut ranges: Vec<(String, Range<i32>)> = functions
+        .values()
+        .map(|function| {
+            let start = function.start_address;
+            let end = (start..quadruplets.len())
+                .find(|i| {
+                    matches!(
+                        quadruplets.get(*i).unwrap().op(),
+                        QuadOperator::EndFunc | QuadOperator::EndProgram
+                    )
+                })
+                .map(|i| i + 1)
+                .unwrap_or(quadruplets.len());
+            (function.name.clone(), start..end)
+        })
+        .collect();
+    ranges.sort_by_key(|(_, range)| range.start);
+    ranges
+}
+
+/// Deletes the quads at `removed` and renumbers every jump target and
+/// function start address. A target that pointed to a deleted quad moves to
+/// the next quad that remains.
+pub fn remove_quads(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+    removed: &HashSet<i32>,
+) {
+    if removed.is_empty() {
+        return;
+    }
+
+    let mut new_positions = Vec::with_capacity(quadruplets.len() as usize + 1);
+    let mut kept = 0;
+    for i in 0..=quadruplets.len() {
+        new_positions.push(kept);
+        if !removed.contains(&i) {
+            kept += 1;
+        }
+    }
+    let new_position = |index: i32| new_positions[index.clamp(0, quadruplets.len()) as usize];
+
+    let mut renumbered = QuadrupletList::new();
+    for (i, quad) in quadruplets.iter().enumerate() {
+        if removed.contains(&(i as i32)) {
+            continue;
+        }
+        let mut quad = quad.clone();
+        match quad.op() {
+            QuadOperator::Goto => quad.arg1 = new_position(quad.arg1),
+            QuadOperator::GotoF | QuadOperator::GotoV => {
+                quad.arg2 = quad.arg2.map(new_position);
+            }
+            _ => {}
+        }
+        renumbered.push(quad);
+    }
+
+    for function in functions.values_mut() {
+        function.start_address = new_position(function.start_address);
+    }
+    *quadruplets = renumbered;
+}
+
+/// Rewrites the reads of `temp` after `index` to `replacement`, until `temp`
+/// is written again. Nothing is changed, and `false` is returned, if
+/// `replacement` is overwritten while `temp` is still being read.
+pub fn replace_reads(
+    quadruplets: &mut QuadrupletList,
+    range: Range<i32>,
+    index: i32,
+    temp: i32,
+    replacement: i32,
+) -> bool {
+    let mut reads = vec![];
+    let mut replacement_written = false;
+    for i in index + 1..range.end {
+        let quad = quadruplets.get(i).unwrap();
+        if quad.reads().contains(&temp) {
+            if replacement_written {
+                return false;
+            }
+            reads.push(i);
+        }
+        if quad.writes() == Some(temp) {
+            break;
+        }
+        if quad.writes() == Some(replacement) {
+            replacement_written = true;
+        }
+    }
+
+    for i in reads {
+        quadruplets.get_mut(i).unwrap().map_reads(|address| {
+            if address == temp {
+                replacement
+            } else {
+                address
+            }
+        });
+    }
+    true
+}
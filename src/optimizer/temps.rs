@@ -0,0 +1,173 @@
+use crate::compiler::memory_layout::{MemoryLayout, Scope};
+use crate::compiler::quadruplets::QuadrupletList;
+use crate::compiler::semantic_cube::Type;
+use crate::compiler::semantic_tables::FunctionTable;
+use crate::optimizer::function_ranges;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result};
+use std::ops::Range;
+
+/// Memory needed by a function before and after recycling its temps, in the
+/// shape of `FunctionInfo::vars_amount`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameReport {
+    pub function: String,
+    pub before: Vec<Vec<i32>>,
+    pub after: Vec<Vec<i32>>,
+}
+
+impl FrameReport {
+    pub fn size_before(&self) -> i32 {
+        self.before.iter().flatten().sum()
+    }
+
+    pub fn size_after(&self) -> i32 {
+        self.after.iter().flatten().sum()
+    }
+}
+
+impl Display for FrameReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "{}: {} -> {} values",
+            self.function,
+            self.size_before(),
+            self.size_after()
+        )
+    }
+}
+
+struct Interval {
+    address: i32,
+    var_type: Type,
+    start: i32,
+    end: i32,
+}
+
+/// Reassigns the temps of every function so temps whose live ranges don't
+/// overlap share an address, and shrinks each `vars_amount` to match.
+pub fn recycle_temps(
+    quadruplets: &mut QuadrupletList,
+    functions: &mut FunctionTable,
+    layout: &MemoryLayout,
+) -> Vec<FrameReport> {
+    let mut reports = vec![];
+    for (name, range) in function_ranges(quadruplets, functions) {
+        let counts = recycle_range(quadruplets, range, layout);
+
+        let function = functions.get_mut(&name).unwrap();
+        let before = function.vars_amount.clone();
+        for (type_index, count) in counts.iter().enumerate() {
+            if let Some(sizes) = function.vars_amount.get_mut(type_index) {
+                sizes[1] = *count;
+            }
+        }
+        reports.push(FrameReport {
+            function: name,
+            before,
+            after: function.vars_amount.clone(),
+        });
+    }
+    reports
+}
+
+fn live_intervals(
+    quadruplets: &QuadrupletList,
+    range: &Range<i32>,
+    layout: &MemoryLayout,
+) -> Vec<Interval> {
+    let mut intervals: HashMap<i32, Interval> = HashMap::new();
+    let mut loops = vec![];
+
+    for i in range.clone() {
+        let quad = quadruplets.get(i).unwrap();
+        for address in quad.reads().into_iter().chain(quad.writes()) {
+            let segment = match layout.decode(address) {
+                Some(segment) if segment.scope == Scope::Temp => segment,
+                _ => continue,
+            };
+            let interval = intervals.entry(address).or_insert(Interval {
+                address,
+                var_type: segment.var_type.clone(),
+                start: i,
+                end: i,
+            });
+            interval.end = i;
+        }
+
+        if let Some(target) = quad.jump_target() {
+            if target <= i && range.contains(&target) {
+                loops.push((target, i));
+            }
+        }
+    }
+
+    // A temp that is live when a loop starts must stay live until its back edge
+    let mut intervals: Vec<Interval> = intervals.into_values().collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for interval in intervals.iter_mut() {
+            for (header, back_edge) in &loops {
+                if interval.start < *header && interval.end >= *header && interval.end < *back_edge
+                {
+                    interval.end = *back_edge;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    intervals.sort_by_key(|interval| (interval.start, interval.address));
+    intervals
+}
+
+// Returns how many temps of each type (int, float, bool) the range needs
+fn recycle_range(
+    quadruplets: &mut QuadrupletList,
+    range: Range<i32>,
+    layout: &MemoryLayout,
+) -> Vec<i32> {
+    let types = [Type::Int, Type::Float, Type::Bool];
+    let mut free: Vec<Vec<i32>> = vec![vec![]; types.len()];
+    let mut used = vec![0; types.len()];
+    let mut active: Vec<(i32, usize, i32)> = vec![];
+    let mut new_addresses = HashMap::new();
+
+    for interval in live_intervals(quadruplets, &range, layout) {
+        let type_index = types.iter().position(|t| *t == interval.var_type).unwrap();
+
+        // Every temp is written before it's read, and a quad reads its
+        // operands before writing its result, so a temp whose last use is
+        // this quad can already hold the new one.
+        active.retain(|(end, index, slot)| {
+            if *end <= interval.start {
+                free[*index].push(*slot);
+                false
+            } else {
+                true
+            }
+        });
+        free[type_index].sort_unstable_by(|a, b| b.cmp(a));
+
+        let slot = free[type_index].pop().unwrap_or_else(|| {
+            used[type_index] += 1;
+            used[type_index] - 1
+        });
+        let base = layout
+            .segment(&interval.var_type, Scope::Temp)
+            .unwrap()
+            .base;
+        new_addresses.insert(interval.address, base + slot);
+        active.push((interval.end, type_index, slot));
+    }
+
+    for i in range {
+        quadruplets
+            .get_mut(i)
+            .unwrap()
+            .map_addresses(|address| *new_addresses.get(&address).unwrap_or(&address));
+    }
+    used
+}
@@ -1,3 +1,4 @@
 pub mod node;
 pub mod queue;
+pub mod random;
 pub mod stack;
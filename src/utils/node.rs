@@ -9,6 +9,10 @@ impl<T> Node<T> {
     pub fn new(value: T) -> Self {
         Node { value, next: None }
     }
+    pub fn next(&self) -> Option<&Node<T>> {
+        self.next.as_deref()
+    }
+
     pub fn get_next(&mut self) -> Option<Box<Node<T>>> {
         self.next.take()
     }
@@ -0,0 +1,47 @@
+/// SplitMix64 pseudo-random numbers. The whole state is one number, so it
+/// can be saved in a snapshot and put back by the undo log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `[low, high]`, which can't be empty.
+    pub fn next_int(&mut self, low: i64, high: i64) -> i64 {
+        let span = (high as i128 - low as i128 + 1) as u128;
+        if span > u64::MAX as u128 {
+            return self.next_u64() as i64;
+        }
+        // Draws past the last whole multiple of the span are thrown away, so
+        // every value is as likely
+        let span = span as u64;
+        let leftover = (u64::MAX % span + 1) % span;
+        loop {
+            let draw = self.next_u64();
+            if draw <= u64::MAX - leftover {
+                return (low as i128 + (draw % span) as i128) as i64;
+            }
+        }
+    }
+}
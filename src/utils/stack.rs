@@ -4,6 +4,12 @@ pub struct Stack<T> {
     size: usize,
 }
 
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Stack<T> {
     pub fn new() -> Self {
         Stack { top: None, size: 0 }
@@ -34,6 +40,11 @@ impl<T> Stack<T> {
         self.top.as_ref().map(|node| &node.value)
     }
 
+    /// Values from the top of the stack to the bottom.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        std::iter::successors(self.top.as_deref(), |node| node.next()).map(|node| &node.value)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.top.is_none()
     }
@@ -0,0 +1,215 @@
+use crate::compiler::compiled_program::CompiledProgram;
+use crate::compiler::quadruplets::QuadOperator;
+use crate::compiler::value_table::Value;
+use crate::vm::runtime_error::RuntimeError;
+use crate::vm::snapshot::Snapshot;
+use crate::vm::virtual_machine::{CallFrame, VirtualMachine};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    Quad(i32),
+    Line(usize),
+}
+
+/// Why the debugger gave control back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stop {
+    Breakpoint(i32),
+    Paused(i32),
+    Finished,
+}
+
+/// Runs a program one piece at a time. Lines are only known when the
+/// debugger gets the source the program was compiled from.
+pub struct Debugger<'a> {
+    machine: VirtualMachine<'a>,
+    breakpoints: BTreeSet<i32>,
+    lines: Vec<Option<usize>>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a CompiledProgram) -> Self {
+        let mut machine = VirtualMachine::new(program);
+        machine.reset();
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            lines: vec![None; program.quadruplets.len() as usize],
+        }
+    }
+
+    pub fn with_source(program: &'a CompiledProgram, source: &str) -> Self {
+        let mut debugger = Debugger::new(program);
+        debugger.lines = program.source_lines(source);
+        debugger
+    }
+
+    /// Continues from a snapshot of the same program, such as the state of a
+    /// run that failed.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+        self.machine.restore(snapshot)
+    }
+
+    pub fn machine(&self) -> &VirtualMachine<'a> {
+        &self.machine
+    }
+
+    pub fn instruction_pointer(&self) -> i32 {
+        self.machine.instruction_pointer()
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line_of(self.instruction_pointer())
+    }
+
+    fn line_of(&self, quad: i32) -> Option<usize> {
+        self.lines.get(quad as usize).copied().flatten()
+    }
+
+    // A line stops at its first quad and wherever a jump lands on it, so a
+    // loop stops once per iteration
+    fn quads_of_line(&self, line: usize) -> Vec<i32> {
+        let program = self.machine.program();
+        let targets: BTreeSet<i32> = program
+            .quadruplets
+            .iter()
+            .filter_map(|quad| quad.jump_target())
+            .chain(
+                program
+                    .functions
+                    .values()
+                    .map(|function| function.start_address),
+            )
+            .collect();
+        let first = (0..program.quadruplets.len()).find(|i| self.line_of(*i) == Some(line));
+        (0..program.quadruplets.len())
+            .filter(|i| self.line_of(*i) == Some(line))
+            .filter(|i| Some(*i) == first || targets.contains(i))
+            .collect()
+    }
+
+    fn resolve(&self, breakpoint: &Breakpoint) -> Result<Vec<i32>, String> {
+        match breakpoint {
+            Breakpoint::Quad(quad) if (0..self.lines.len() as i32).contains(quad) => {
+                Ok(vec![*quad])
+            }
+            Breakpoint::Quad(quad) => Err(format!("There's no quad {}", quad)),
+            Breakpoint::Line(line) => match self.quads_of_line(*line) {
+                quads if quads.is_empty() => Err(format!("Line {} has no code", line)),
+                quads => Ok(quads),
+            },
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> Result<(), String> {
+        let quads = self.resolve(&breakpoint)?;
+        self.breakpoints.extend(quads);
+        Ok(())
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> Result<(), String> {
+        for quad in self.resolve(&breakpoint)? {
+            self.breakpoints.remove(&quad);
+        }
+        Ok(())
+    }
+
+    // Steps until `done` holds or a breakpoint is reached
+    fn run_until(&mut self, done: impl Fn(&Self) -> bool) -> Result<Stop, RuntimeError> {
+        loop {
+            if self.machine.is_finished() {
+                return Ok(Stop::Finished);
+            }
+            self.machine.step()?;
+            let instruction_pointer = self.instruction_pointer();
+            if self.machine.is_finished() {
+                return Ok(Stop::Finished);
+            }
+            if self.breakpoints.contains(&instruction_pointer) {
+                return Ok(Stop::Breakpoint(instruction_pointer));
+            }
+            if done(self) {
+                return Ok(Stop::Paused(instruction_pointer));
+            }
+        }
+    }
+
+    /// Runs until a breakpoint or the end of the program.
+    pub fn resume(&mut self) -> Result<Stop, RuntimeError> {
+        self.run_until(|_| false)
+    }
+
+    /// Runs one quad, entering the function of a `GOSUB`.
+    pub fn step_into(&mut self) -> Result<Stop, RuntimeError> {
+        self.run_until(|_| true)
+    }
+
+    /// Runs one quad, or a whole call when the quad is a `GOSUB`.
+    pub fn step_over(&mut self) -> Result<Stop, RuntimeError> {
+        let program = self.machine.program();
+        let depth = self.machine.call_depth();
+        match program.quadruplets.get(self.instruction_pointer()) {
+            Some(quad) if quad.op() == QuadOperator::GoSub => {
+                self.run_until(|debugger| debugger.machine.call_depth() <= depth)
+            }
+            _ => self.step_into(),
+        }
+    }
+
+    /// Runs until the running function returns to its caller.
+    pub fn step_out(&mut self) -> Result<Stop, RuntimeError> {
+        let depth = self.machine.call_depth();
+        self.run_until(|debugger| debugger.machine.call_depth() < depth)
+    }
+
+    // Variables of the running function hide the globals
+    fn address_of(&self, name: &str) -> Option<i32> {
+        let functions = &self.machine.program().functions;
+        let variable = functions
+            .get(&self.machine.current_function())
+            .and_then(|function| function.vars.get(name))
+            .or_else(|| functions.get("main")?.vars.get(name))?;
+        Some(variable.address)
+    }
+
+    /// Value of a variable or constant of the running function, or of a
+    /// global.
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        Some(self.machine.get_value(self.address_of(name)?))
+    }
+
+    /// Keeps the undo log `step_back`, `back_to_last_write` and `rewind` need.
+    pub fn record_history(&mut self, enabled: bool) {
+        self.machine.record_history(enabled);
+    }
+
+    /// Undoes the last quad run.
+    pub fn step_back(&mut self) -> Result<Stop, String> {
+        match self.machine.step_back() {
+            true => Ok(Stop::Paused(self.instruction_pointer())),
+            false => Err("There's nothing to undo".to_string()),
+        }
+    }
+
+    /// Goes back to the quad that last wrote a variable, before it runs.
+    pub fn back_to_last_write(&mut self, name: &str) -> Result<Stop, String> {
+        let address = self
+            .address_of(name)
+            .ok_or_else(|| format!("There's no variable {}", name))?;
+        match self.machine.back_to_last_write(address) {
+            true => Ok(Stop::Paused(self.instruction_pointer())),
+            false => Err(format!("The history has no write of {}", name)),
+        }
+    }
+
+    /// Goes back until only `executed_quads` quads have run.
+    pub fn rewind(&mut self, executed_quads: u64) -> Result<Stop, String> {
+        self.machine.rewind(executed_quads)?;
+        Ok(Stop::Paused(self.instruction_pointer()))
+    }
+
+    pub fn call_stack(&self) -> Vec<CallFrame> {
+        self.machine.call_stack()
+    }
+}
@@ -0,0 +1,37 @@
+use crate::compiler::value_table::{Memory, Value};
+
+/// What a quad changed besides the instruction pointer, with what's needed to
+/// put it back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A value was written to an address of the running function, or of the
+    /// frame being prepared for a call when `upcoming` is set (`PARAM`).
+    Write {
+        address: i32,
+        previous: Value,
+        upcoming: bool,
+    },
+    /// A `PARAM` without a target passed an argument to a builtin.
+    Argument,
+    /// `CALLBUILTIN` used up the arguments passed to it and wrote its result.
+    /// `random` is the state of the random numbers before the call.
+    Builtin {
+        arguments: Vec<Value>,
+        address: i32,
+        previous: Value,
+        random: u64,
+    },
+    /// `MEMORY` replaced the frame being prepared for a call.
+    Prepare { previous: Option<Memory> },
+    /// `GOSUB` started running the prepared frame.
+    Call,
+    /// `ENDFUNC` dropped a frame and jumped to its return address.
+    Return { frame: Memory, return_address: i32 },
+}
+
+/// One executed quad of the undo log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub instruction_pointer: i32,
+    pub change: Option<Change>,
+}
@@ -0,0 +1,13 @@
+use std::time::Duration;
+
+/// Bounds on what a single run can use, so a program that never ends or
+/// recurses forever stops with an error instead. `None` means no bound.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    pub max_quads: Option<u64>,
+    pub max_call_depth: Option<usize>,
+    /// Values held by all the frames of the functions called so far.
+    pub max_frame_memory: Option<usize>,
+    /// Wall-clock time since the run started.
+    pub deadline: Option<Duration>,
+}
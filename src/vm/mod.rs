@@ -0,0 +1,8 @@
+pub mod debugger;
+pub mod history;
+pub mod limits;
+pub mod profiler;
+pub mod runtime_error;
+pub mod snapshot;
+pub mod tracer;
+pub mod virtual_machine;
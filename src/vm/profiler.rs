@@ -0,0 +1,207 @@
+use crate::compiler::compiled_program::CompiledProgram;
+use crate::compiler::disassembler::decode;
+use crate::compiler::quadruplets::QuadOperator;
+use crate::vm::runtime_error::RuntimeError;
+use crate::vm::virtual_machine::VirtualMachine;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// What a single function did during a run. `quads` and `time` only count
+/// the function's own quads, not the ones of the functions it calls.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionProfile {
+    pub calls: u64,
+    pub frames: u64,
+    pub quads: u64,
+    pub time: Duration,
+}
+
+/// Counts collected by `Profiler::run`.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub quads: Vec<u64>,
+    pub opcodes: HashMap<QuadOperator, u64>,
+    pub lines: BTreeMap<usize, u64>,
+    pub functions: HashMap<String, FunctionProfile>,
+    /// Quads executed under each call stack, as `main;f;g`.
+    pub stacks: HashMap<String, u64>,
+    instructions: Vec<String>,
+}
+
+// Most executed first, ties broken by key so reports are stable
+fn sorted<K: Ord + Clone>(counts: impl Iterator<Item = (K, u64)>) -> Vec<(K, u64)> {
+    let mut counts: Vec<(K, u64)> = counts.collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+impl Profile {
+    fn function(&mut self, name: &str) -> &mut FunctionProfile {
+        self.functions.entry(name.to_string()).or_default()
+    }
+
+    /// Sorted tables of functions, opcodes, lines and the most executed quads.
+    pub fn table(&self) -> String {
+        let mut text = String::new();
+        writeln!(
+            text,
+            "{:<20} {:>8} {:>8} {:>10} {:>12}",
+            "function", "calls", "frames", "quads", "time (ms)"
+        )
+        .unwrap();
+        let functions = sorted(
+            self.functions
+                .iter()
+                .map(|(name, function)| (name.clone(), function.quads)),
+        );
+        for (name, _) in functions {
+            let function = &self.functions[&name];
+            writeln!(
+                text,
+                "{:<20} {:>8} {:>8} {:>10} {:>12.3}",
+                name,
+                function.calls,
+                function.frames,
+                function.quads,
+                function.time.as_secs_f64() * 1000.0
+            )
+            .unwrap();
+        }
+
+        writeln!(text, "\n{:<20} {:>10}", "opcode", "count").unwrap();
+        let opcodes = sorted(self.opcodes.iter().map(|(op, count)| (op.name(), *count)));
+        for (name, count) in opcodes {
+            writeln!(text, "{:<20} {:>10}", name, count).unwrap();
+        }
+
+        if !self.lines.is_empty() {
+            writeln!(text, "\n{:<20} {:>10}", "line", "count").unwrap();
+            for (line, count) in sorted(self.lines.iter().map(|(line, count)| (*line, *count))) {
+                writeln!(text, "{:<20} {:>10}", line, count).unwrap();
+            }
+        }
+
+        writeln!(
+            text,
+            "\n{:<6} {:<40} {:>10}",
+            "quad", "instruction", "count"
+        )
+        .unwrap();
+        let quads = sorted(
+            self.quads
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(quad, count)| (quad, *count)),
+        );
+        for (quad, count) in quads.into_iter().take(20) {
+            writeln!(
+                text,
+                "{:<6} {:<40} {:>10}",
+                quad, self.instructions[quad], count
+            )
+            .unwrap();
+        }
+        text
+    }
+
+    /// One `main;f;g count` line per call stack, the input flamegraph tools
+    /// take.
+    pub fn folded(&self) -> String {
+        let stacks: BTreeMap<&String, &u64> = self.stacks.iter().collect();
+        stacks
+            .into_iter()
+            .map(|(stack, count)| format!("{} {}\n", stack, count))
+            .collect()
+    }
+}
+
+/// Runs a program counting what it executes.
+pub struct Profiler<'a> {
+    machine: VirtualMachine<'a>,
+    lines: Vec<Option<usize>>,
+}
+
+impl<'a> Profiler<'a> {
+    pub fn new(program: &'a CompiledProgram) -> Self {
+        Profiler {
+            machine: VirtualMachine::new(program),
+            lines: vec![],
+        }
+    }
+
+    /// Also counts per line of the source the program was compiled from.
+    pub fn with_source(program: &'a CompiledProgram, source: &str) -> Self {
+        let mut profiler = Profiler::new(program);
+        profiler.lines = program.source_lines(source);
+        profiler
+    }
+
+    pub fn run(&mut self) -> Result<Profile, RuntimeError> {
+        let program = self.machine.program();
+        let mut profile = Profile {
+            quads: vec![0; program.quadruplets.len() as usize],
+            opcodes: HashMap::new(),
+            lines: BTreeMap::new(),
+            functions: HashMap::new(),
+            stacks: HashMap::new(),
+            instructions: decode(program)
+                .into_iter()
+                .map(|quad| quad.instruction)
+                .collect(),
+        };
+
+        // Quads and time are added to the running function whenever a call
+        // starts or returns
+        let mut stack = vec!["main".to_string()];
+        let mut pending = 0;
+        let mut started = Instant::now();
+        let mut flush = |profile: &mut Profile, stack: &[String], pending: u64| {
+            let function = profile.function(stack.last().unwrap());
+            function.quads += pending;
+            function.time += started.elapsed();
+            *profile.stacks.entry(stack.join(";")).or_insert(0) += pending;
+            started = Instant::now();
+        };
+
+        self.machine.reset();
+        while !self.machine.is_finished() {
+            let instruction_pointer = self.machine.instruction_pointer();
+            let quad = program.quadruplets.get(instruction_pointer).unwrap();
+            let depth = self.machine.call_depth();
+            self.machine.step()?;
+
+            profile.quads[instruction_pointer as usize] += 1;
+            *profile.opcodes.entry(quad.op()).or_insert(0) += 1;
+            pending += 1;
+            match quad.op() {
+                QuadOperator::Memory => {
+                    profile.function(&program.function_ids[&quad.arg1]).frames += 1
+                }
+                QuadOperator::GoSub => {
+                    profile.function(&program.function_ids[&quad.arg1]).calls += 1
+                }
+                _ => {}
+            }
+
+            if self.machine.call_depth() != depth {
+                flush(&mut profile, &stack, pending);
+                pending = 0;
+                if self.machine.call_depth() > depth {
+                    stack.push(self.machine.current_function());
+                } else {
+                    stack.pop();
+                }
+            }
+        }
+        flush(&mut profile, &stack, pending);
+
+        for (quad, line) in self.lines.iter().enumerate() {
+            if let (Some(line), count @ 1..) = (line, profile.quads[quad]) {
+                *profile.lines.entry(*line).or_insert(0) += count;
+            }
+        }
+        Ok(profile)
+    }
+}
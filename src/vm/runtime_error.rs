@@ -0,0 +1,45 @@
+use std::fmt::{Display, Formatter, Result};
+
+/// What stopped the machine. Everything but `Fault` is a limit of
+/// `vm::limits::Limits` running out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Fault,
+    QuadLimit,
+    CallDepthLimit,
+    MemoryLimit,
+    Deadline,
+}
+
+/// An error that stops the virtual machine, with the index of the quad that
+/// was running.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub instruction_pointer: i32,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, instruction_pointer: i32) -> Self {
+        RuntimeError::with_kind(ErrorKind::Fault, message, instruction_pointer)
+    }
+
+    pub fn with_kind(kind: ErrorKind, message: String, instruction_pointer: i32) -> Self {
+        RuntimeError {
+            kind,
+            message,
+            instruction_pointer,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "runtime error at quad {}: {}",
+            self.instruction_pointer, self.message
+        )
+    }
+}
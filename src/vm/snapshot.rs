@@ -0,0 +1,177 @@
+use crate::compiler::value_table::{Memory, Value};
+
+/// Version written in every snapshot. Snapshots from another version are
+/// rejected when loading.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const HEADER: &str = "pandora-snapshot";
+
+/// Everything a `VirtualMachine` holds while running a program, so a run can
+/// be saved and resumed later from the same point. Frames and return
+/// addresses go from the first call to the last one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub instruction_pointer: i32,
+    pub executed_quads: u64,
+    pub globals: Memory,
+    pub frames: Vec<Memory>,
+    pub return_addresses: Vec<i32>,
+    pub upcoming_function: Option<Memory>,
+    /// Arguments passed to a builtin that hasn't been called yet.
+    pub builtin_args: Vec<Value>,
+    /// State of the random numbers of `rand_int` and `rand_float`.
+    pub random: u64,
+}
+
+impl Snapshot {
+    /// Line based form, with one `frame` and one `return` line per call. A
+    /// memory is written as its six lists of values (int, float and bool, each
+    /// global or local and temp), with `-` for an empty list.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("{} {}", HEADER, SNAPSHOT_VERSION),
+            format!("ip {}", self.instruction_pointer),
+            format!("executed {}", self.executed_quads),
+            format!("random {}", self.random),
+            format!("globals {}", memory_to_text(&self.globals)),
+        ];
+        for frame in &self.frames {
+            lines.push(format!("frame {}", memory_to_text(frame)));
+        }
+        for return_address in &self.return_addresses {
+            lines.push(format!("return {}", return_address));
+        }
+        if let Some(upcoming_function) = &self.upcoming_function {
+            lines.push(format!("upcoming {}", memory_to_text(upcoming_function)));
+        }
+        if !self.builtin_args.is_empty() {
+            lines.push(format!("args {}", values_to_text(&self.builtin_args)));
+        }
+
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        match lines.next().unwrap_or_default().split_once(' ') {
+            Some((HEADER, version)) => {
+                let version: u32 = parse(version, "version")?;
+                if version != SNAPSHOT_VERSION {
+                    return Err(format!(
+                        "Snapshot version {} is not compatible with version {}",
+                        version, SNAPSHOT_VERSION
+                    ));
+                }
+            }
+            _ => return Err("Invalid snapshot header".to_string()),
+        }
+
+        let mut snapshot = Snapshot {
+            instruction_pointer: 0,
+            executed_quads: 0,
+            globals: Memory::new(),
+            frames: vec![],
+            return_addresses: vec![],
+            upcoming_function: None,
+            builtin_args: vec![],
+            random: 0,
+        };
+        for line in lines {
+            let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match kind {
+                "ip" => snapshot.instruction_pointer = parse(rest, "instruction pointer")?,
+                "executed" => snapshot.executed_quads = parse(rest, "quad count")?,
+                "random" => snapshot.random = parse(rest, "random state")?,
+                "globals" => snapshot.globals = memory_from_text(rest)?,
+                "frame" => snapshot.frames.push(memory_from_text(rest)?),
+                "return" => snapshot
+                    .return_addresses
+                    .push(parse(rest, "return address")?),
+                "upcoming" => snapshot.upcoming_function = Some(memory_from_text(rest)?),
+                "args" => snapshot.builtin_args = values_from_text(rest)?,
+                _ => return Err(format!("Unknown entry: {}", line)),
+            }
+        }
+        if snapshot.frames.len() != snapshot.return_addresses.len() {
+            return Err(format!(
+                "The snapshot has {} frames but {} return addresses",
+                snapshot.frames.len(),
+                snapshot.return_addresses.len()
+            ));
+        }
+        Ok(snapshot)
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid {}: {}", what, value))
+}
+
+// Floats use `{:?}` so they're read back exactly, always with a `.`
+fn values_to_text(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::Float(value) => format!("{:?}", value),
+            value => value.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Values of any type, told apart by how they're written
+fn values_from_text(text: &str) -> Result<Vec<Value>, String> {
+    text.trim()
+        .split(',')
+        .map(|value| {
+            if let Ok(value) = value.parse() {
+                Ok(Value::Int(value))
+            } else if let Ok(value) = value.parse() {
+                Ok(Value::Bool(value))
+            } else {
+                parse(value, "value").map(Value::Float)
+            }
+        })
+        .collect()
+}
+
+fn memory_to_text(memory: &Memory) -> String {
+    memory
+        .values
+        .iter()
+        .flatten()
+        .map(|values| match values.is_empty() {
+            true => "-".to_string(),
+            false => values_to_text(values),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn memory_from_text(text: &str) -> Result<Memory, String> {
+    let lists: Vec<&str> = text.split_whitespace().collect();
+    if lists.len() != 6 {
+        return Err(format!("A memory needs 6 lists of values: {}", text));
+    }
+
+    let mut memory = Memory::new();
+    for (index, list) in lists.into_iter().enumerate() {
+        if list == "-" {
+            continue;
+        }
+        memory.values[index / 2][index % 2] = list
+            .split(',')
+            .map(|value| match index / 2 {
+                0 => parse(value, "int").map(Value::Int),
+                1 => parse(value, "float").map(Value::Float),
+                _ => parse(value, "bool").map(Value::Bool),
+            })
+            .collect::<Result<Vec<Value>, String>>()?;
+    }
+    Ok(memory)
+}
@@ -0,0 +1,164 @@
+use crate::compiler::compiled_program::CompiledProgram;
+use crate::compiler::disassembler::{decode, DecodedQuad};
+use crate::compiler::value_table::Value;
+use crate::vm::runtime_error::RuntimeError;
+use crate::vm::virtual_machine::VirtualMachine;
+use std::collections::HashSet;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    /// One line per quad, like `12 fibonacci: SUM a, b -> t1  ; t1 = 8`, and
+    /// `-> name` / `<- name` when a function starts or returns.
+    Text,
+    /// One JSON object per line, with an `event` of `quad`, `enter` or `exit`.
+    JsonLines,
+}
+
+/// Runs a program writing every quad it executes to a sink, so two runs can
+/// be compared line by line.
+pub struct Tracer<'a, W: Write> {
+    machine: VirtualMachine<'a>,
+    decoded: Vec<DecodedQuad>,
+    sink: W,
+    format: TraceFormat,
+    functions: Option<HashSet<String>>,
+}
+
+impl<'a, W: Write> Tracer<'a, W> {
+    pub fn new(program: &'a CompiledProgram, sink: W, format: TraceFormat) -> Self {
+        Tracer {
+            machine: VirtualMachine::new(program),
+            decoded: decode(program),
+            sink,
+            format,
+            functions: None,
+        }
+    }
+
+    /// Only traces the quads of these functions, and their entries and exits.
+    pub fn with_filter(mut self, functions: &[&str]) -> Self {
+        self.functions = Some(functions.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    pub fn into_sink(self) -> W {
+        self.sink
+    }
+
+    fn is_traced(&self, function: &str) -> bool {
+        self.functions
+            .as_ref()
+            .is_none_or(|functions| functions.contains(function))
+    }
+
+    fn emit(&mut self, function: &str, line: String) -> Result<(), RuntimeError> {
+        if !self.is_traced(function) {
+            return Ok(());
+        }
+        writeln!(self.sink, "{}", line).map_err(|error| {
+            RuntimeError::new(
+                format!("Unable to write the trace: {}", error),
+                self.machine.instruction_pointer(),
+            )
+        })
+    }
+
+    fn call_event(&mut self, event: &str, function: &str) -> Result<(), RuntimeError> {
+        let line = match self.format {
+            TraceFormat::Text if event == "enter" => format!("-> {}", function),
+            TraceFormat::Text => format!("<- {}", function),
+            TraceFormat::JsonLines => format!(
+                "{{\"event\":\"{}\",\"function\":{}}}",
+                event,
+                json_string(function)
+            ),
+        };
+        self.emit(function, line)
+    }
+
+    fn quad_event(&mut self, instruction_pointer: i32, function: &str) -> Result<(), RuntimeError> {
+        let decoded = &self.decoded[instruction_pointer as usize];
+        let write = match (&decoded.target, self.machine.last_write()) {
+            (Some(target), Some((_, value))) => Some((target.clone(), value.clone())),
+            _ => None,
+        };
+        let line = match self.format {
+            TraceFormat::Text => {
+                let mut line = format!(
+                    "{} {}: {}",
+                    instruction_pointer, function, decoded.instruction
+                );
+                if let Some((target, value)) = &write {
+                    let arrow = if decoded.writes_temp { "=" } else { "<-" };
+                    line.push_str(&format!("  ; {} {} {}", target, arrow, value));
+                }
+                line
+            }
+            TraceFormat::JsonLines => {
+                let mut line = format!(
+                    "{{\"event\":\"quad\",\"ip\":{},\"function\":{},\"instruction\":{}",
+                    instruction_pointer,
+                    json_string(function),
+                    json_string(&decoded.instruction)
+                );
+                if let Some((target, value)) = &write {
+                    line.push_str(&format!(
+                        ",\"target\":{},\"value\":{}",
+                        json_string(target),
+                        json_value(value)
+                    ));
+                }
+                line.push('}');
+                line
+            }
+        };
+        self.emit(function, line)
+    }
+
+    /// Runs the program from the start, tracing until it ends or fails.
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        self.machine.reset();
+        self.call_event("enter", "main")?;
+        while !self.machine.is_finished() {
+            let instruction_pointer = self.machine.instruction_pointer();
+            let function = self.machine.current_function();
+            let depth = self.machine.call_depth();
+
+            // The quad that fails is traced too, as the last one
+            let result = self.machine.step();
+            self.quad_event(instruction_pointer, &function)?;
+            result?;
+            if self.machine.call_depth() > depth {
+                let callee = self.machine.current_function();
+                self.call_event("enter", &callee)?;
+            } else if self.machine.call_depth() < depth {
+                self.call_event("exit", &function)?;
+            }
+        }
+        self.call_event("exit", "main")
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// JSON has no infinity or NaN, so those floats are written as null
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Float(value) if !value.is_finite() => "null".to_string(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,767 @@
+use crate::compiler::builtins::{call_error, BuiltinFunction};
+use crate::compiler::compiled_program::CompiledProgram;
+use crate::compiler::memory_layout::Scope;
+use crate::compiler::quadruplets::{
+    convert_quad_op_to_code, convert_quad_op_to_semantic_op, QuadOperator,
+};
+use crate::compiler::semantic_cube::{CuboSemantico, Type};
+use crate::compiler::semantic_tables::FunctionInfo;
+use crate::compiler::value_table::{type_name, Memory, Value, ValueTable};
+use crate::utils::random::Random;
+use crate::vm::history::{Change, Entry};
+use crate::vm::limits::Limits;
+use crate::vm::runtime_error::{ErrorKind, RuntimeError};
+use crate::vm::snapshot::Snapshot;
+use crate::Stack;
+use std::io::Write;
+use std::time::Instant;
+
+/// A function waiting for a call to return, or the one running when it's
+/// the first of `VirtualMachine::call_stack`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    pub function: String,
+    pub instruction_pointer: i32,
+}
+
+/// Runs a compiled program. The program is only read, so it can be shared by
+/// any number of machines, each one with its own memory.
+pub struct VirtualMachine<'a> {
+    program: &'a CompiledProgram,
+    cubo: CuboSemantico,
+    value_table: ValueTable,
+    memory_stack: Stack<Memory>,
+    position_before_fcall: Stack<i32>,
+    upcoming_function: Option<Memory>,
+    instruction_pointer: i32,
+    last_write: Option<(i32, Value)>,
+    limits: Limits,
+    executed_quads: u64,
+    frame_memory: usize,
+    started: Instant,
+    history: Option<Vec<Entry>>,
+    change: Option<Change>,
+    builtin_args: Vec<Value>,
+    seed: u64,
+    random: Random,
+    output: Box<dyn Write + 'a>,
+}
+
+impl<'a> VirtualMachine<'a> {
+    pub fn new(program: &'a CompiledProgram) -> Self {
+        let mut value_table = ValueTable::with_layout(program.layout.clone());
+        value_table.constants = program.constants.clone();
+        value_table.reset_memory(&program.globals);
+
+        VirtualMachine {
+            program,
+            cubo: CuboSemantico::new(),
+            value_table,
+            memory_stack: Stack::new(),
+            position_before_fcall: Stack::new(),
+            upcoming_function: None,
+            instruction_pointer: 0,
+            last_write: None,
+            limits: Limits::default(),
+            executed_quads: 0,
+            frame_memory: 0,
+            started: Instant::now(),
+            history: None,
+            change: None,
+            builtin_args: vec![],
+            seed: 0,
+            random: Random::new(0),
+            output: Box::new(std::io::stdout()),
+        }
+    }
+
+    /// A machine that stops with an error once a run goes past `limits`.
+    pub fn with_limits(program: &'a CompiledProgram, limits: Limits) -> Self {
+        let mut machine = VirtualMachine::new(program);
+        machine.limits = limits;
+        machine
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Where `PRINT` writes, standard output unless changed.
+    pub fn set_output(&mut self, output: impl Write + 'a) {
+        self.output = Box::new(output);
+    }
+
+    /// Seeds the random numbers of `rand_int` and `rand_float`, so runs with
+    /// the same seed draw the same numbers. Every `reset` starts over from it.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.random = Random::new(seed);
+    }
+
+    /// Puts the machine back at the first quad with fresh memory.
+    pub fn reset(&mut self) {
+        self.value_table.reset_memory(&self.program.globals);
+        self.memory_stack = Stack::new();
+        self.position_before_fcall = Stack::new();
+        self.upcoming_function = None;
+        self.instruction_pointer = 0;
+        self.last_write = None;
+        self.executed_quads = 0;
+        self.frame_memory = 0;
+        self.started = Instant::now();
+        self.builtin_args.clear();
+        self.random = Random::new(self.seed);
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+    }
+
+    /// Starts or stops keeping an undo log of every quad run from now on, so
+    /// the machine can go back with `step_back`, `back_to_last_write` and
+    /// `rewind`. Printed output can't be taken back.
+    pub fn record_history(&mut self, enabled: bool) {
+        self.history = enabled.then(Vec::new);
+    }
+
+    /// Quads in the undo log, oldest first.
+    pub fn history(&self) -> &[Entry] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    /// Undoes the last quad run, returning false when the log is empty.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.as_mut().and_then(Vec::pop) {
+            Some(entry) => {
+                self.undo(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Goes back to the quad that last wrote an address, as seen from the
+    /// running function. Locals are only searched back to the `MEMORY` that
+    /// created their frame. Returns false, without moving, when there's no
+    /// such write in the log.
+    pub fn back_to_last_write(&mut self, address: i32) -> bool {
+        let is_local = self
+            .value_table
+            .layout
+            .decode(address)
+            .is_some_and(|segment| segment.scope != Scope::Global);
+        let start = self.call_depth();
+
+        // Depth each entry ran at, following calls and returns backwards
+        let mut depth = start;
+        let mut found = None;
+        for (index, entry) in self.history().iter().enumerate().rev() {
+            match &entry.change {
+                Some(Change::Call) => depth -= 1,
+                Some(Change::Return { .. }) => depth += 1,
+                _ => {}
+            }
+            match &entry.change {
+                // Parameters are written into the frame before it's called
+                Some(Change::Write {
+                    address: written,
+                    upcoming,
+                    ..
+                }) if *written == address && (!is_local || depth + *upcoming as usize == start) => {
+                    found = Some(index);
+                    break;
+                }
+                Some(Change::Prepare { .. }) if is_local && depth + 1 == start => break,
+                _ => {}
+            }
+            if is_local && depth + 1 < start {
+                break;
+            }
+        }
+
+        match found {
+            Some(index) => {
+                while self.history().len() > index {
+                    self.step_back();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Goes back until only `executed_quads` quads have run.
+    pub fn rewind(&mut self, executed_quads: u64) -> Result<(), String> {
+        let oldest = self.executed_quads - self.history().len() as u64;
+        if executed_quads < oldest {
+            return Err(format!(
+                "The history doesn't go back to {} executed quads",
+                executed_quads
+            ));
+        }
+        while self.executed_quads > executed_quads {
+            self.step_back();
+        }
+        Ok(())
+    }
+
+    // Values are put back with the typed setters of the value table
+    fn put_back(&mut self, address: i32, previous: Value, upcoming: bool) {
+        let memory = match upcoming {
+            true => self.upcoming_function.as_mut(),
+            false => self.memory_stack.top_mut(),
+        };
+        match previous {
+            Value::Int(value) => self.value_table.set_int(address, value, memory),
+            Value::Float(value) => self.value_table.set_float(address, value, memory),
+            Value::Bool(value) => self.value_table.set_bool(address, value, memory),
+        }
+    }
+
+    fn undo(&mut self, entry: Entry) {
+        self.instruction_pointer = entry.instruction_pointer;
+        self.executed_quads -= 1;
+        self.last_write = None;
+        match entry.change {
+            Some(Change::Write {
+                address,
+                previous,
+                upcoming,
+            }) => self.put_back(address, previous, upcoming),
+            Some(Change::Argument) => {
+                self.builtin_args.pop();
+            }
+            Some(Change::Builtin {
+                arguments,
+                address,
+                previous,
+                random,
+            }) => {
+                self.builtin_args = arguments;
+                self.random = Random::new(random);
+                self.put_back(address, previous, false);
+            }
+            Some(Change::Prepare { previous }) => {
+                let size = |memory: &Option<Memory>| memory.as_ref().map_or(0, Memory::size);
+                self.frame_memory =
+                    self.frame_memory - size(&self.upcoming_function) + size(&previous);
+                self.upcoming_function = previous;
+            }
+            Some(Change::Call) => {
+                self.upcoming_function = self.memory_stack.pop();
+                self.position_before_fcall.pop();
+            }
+            Some(Change::Return {
+                frame,
+                return_address,
+            }) => {
+                self.frame_memory += frame.size();
+                self.memory_stack.push(frame);
+                self.position_before_fcall.push(return_address);
+            }
+            None => {}
+        }
+    }
+
+    /// State of the machine at the current quad.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut frames: Vec<Memory> = self.memory_stack.iter().cloned().collect();
+        frames.reverse();
+        let mut return_addresses: Vec<i32> = self.position_before_fcall.iter().copied().collect();
+        return_addresses.reverse();
+        Snapshot {
+            instruction_pointer: self.instruction_pointer,
+            executed_quads: self.executed_quads,
+            globals: self.value_table.memory().clone(),
+            frames,
+            return_addresses,
+            upcoming_function: self.upcoming_function.clone(),
+            builtin_args: self.builtin_args.clone(),
+            random: self.random.state(),
+        }
+    }
+
+    /// Puts the machine in the state of a snapshot taken while running the
+    /// same program, checking that its memories fit the program.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), String> {
+        let program = self.program;
+        if !(0..=program.quadruplets.len()).contains(&snapshot.instruction_pointer) {
+            return Err(format!(
+                "There's no quad {} to resume from",
+                snapshot.instruction_pointer
+            ));
+        }
+        if shape(&snapshot.globals) != shape(&Memory::with_sizes(&program.globals)) {
+            return Err("The global memory doesn't fit the program".to_string());
+        }
+        if snapshot.frames.len() != snapshot.return_addresses.len() {
+            return Err("Every frame needs a return address".to_string());
+        }
+        for (frame, return_address) in snapshot.frames.iter().zip(&snapshot.return_addresses) {
+            let function = program
+                .quadruplets
+                .get(return_address - 1)
+                .filter(|quad| quad.op() == QuadOperator::GoSub)
+                .and_then(|gosub| program.function_ids.get(&gosub.arg1))
+                .ok_or_else(|| format!("Quad {} doesn't return from a call", return_address))?;
+            if shape(frame)
+                != shape(&Memory::with_sizes(
+                    &program.functions[function].vars_amount,
+                ))
+            {
+                return Err(format!("The frame of {} doesn't fit the program", function));
+            }
+        }
+        if let Some(upcoming_function) = &snapshot.upcoming_function {
+            let fits = program.functions.values().any(|function| {
+                shape(upcoming_function) == shape(&Memory::with_sizes(&function.vars_amount))
+            });
+            if !fits {
+                return Err("The frame being called doesn't fit the program".to_string());
+            }
+        }
+
+        self.reset();
+        self.value_table.set_memory(snapshot.globals.clone());
+        for (frame, return_address) in snapshot.frames.iter().zip(&snapshot.return_addresses) {
+            self.memory_stack.push(frame.clone());
+            self.position_before_fcall.push(*return_address);
+        }
+        self.upcoming_function = snapshot.upcoming_function.clone();
+        self.builtin_args = snapshot.builtin_args.clone();
+        self.random = Random::new(snapshot.random);
+        self.instruction_pointer = snapshot.instruction_pointer;
+        self.executed_quads = snapshot.executed_quads;
+        self.frame_memory = self
+            .memory_stack
+            .iter()
+            .chain(&self.upcoming_function)
+            .map(Memory::size)
+            .sum();
+        Ok(())
+    }
+
+    /// Address and value written by the last quad, if it wrote one.
+    pub fn last_write(&self) -> Option<&(i32, Value)> {
+        self.last_write.as_ref()
+    }
+
+    /// Value at an address, read from the frame of the running function when
+    /// it's a local or a temp.
+    pub fn get_value(&self, address: i32) -> Value {
+        self.value_table.get_value(address, self.memory_stack.top())
+    }
+
+    /// Quads run since the last reset.
+    pub fn executed_quads(&self) -> u64 {
+        self.executed_quads
+    }
+
+    /// Values of the variables of `main`, sorted by name. Constants are left
+    /// out.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut values: Vec<(String, Value)> = self
+            .program
+            .functions
+            .get("main")
+            .map(|main| main.vars.values())
+            .into_iter()
+            .flatten()
+            .filter(|var| self.program.constants.get(var.address).is_none())
+            .map(|var| (var.name.clone(), self.get_value(var.address)))
+            .collect();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
+    pub fn program(&self) -> &'a CompiledProgram {
+        self.program
+    }
+
+    pub fn instruction_pointer(&self) -> i32 {
+        self.instruction_pointer
+    }
+
+    // Function called by the `GOSUB` right before a return address
+    fn called_function(&self, return_address: i32) -> String {
+        let gosub = self.program.quadruplets.get(return_address - 1).unwrap();
+        self.program.function_ids[&gosub.arg1].clone()
+    }
+
+    /// Name of the running function.
+    pub fn current_function(&self) -> String {
+        match self.position_before_fcall.top() {
+            Some(return_address) => self.called_function(*return_address),
+            None => "main".to_string(),
+        }
+    }
+
+    /// How many calls haven't returned yet.
+    pub fn call_depth(&self) -> usize {
+        self.position_before_fcall.size()
+    }
+
+    /// Running function first, then each caller stopped at its `GOSUB`.
+    pub fn call_stack(&self) -> Vec<CallFrame> {
+        let mut frames = vec![];
+        let mut instruction_pointer = self.instruction_pointer;
+        for return_address in self.position_before_fcall.iter() {
+            frames.push(CallFrame {
+                function: self.called_function(*return_address),
+                instruction_pointer,
+            });
+            instruction_pointer = return_address - 1;
+        }
+        frames.push(CallFrame {
+            function: "main".to_string(),
+            instruction_pointer,
+        });
+        frames
+    }
+
+    fn error(&self, message: String) -> RuntimeError {
+        RuntimeError::new(message, self.instruction_pointer)
+    }
+
+    fn print(&mut self, text: &dyn std::fmt::Display) -> Result<(), RuntimeError> {
+        writeln!(self.output, "{}", text)
+            .map_err(|error| self.error(format!("Unable to write the output: {}", error)))
+    }
+
+    // Limit errors also say which function was running
+    fn limit_error(&self, kind: ErrorKind, message: String) -> RuntimeError {
+        RuntimeError::with_kind(
+            kind,
+            format!("{} in {}", message, self.current_function()),
+            self.instruction_pointer,
+        )
+    }
+
+    fn check_limits(&self) -> Result<(), RuntimeError> {
+        if let Some(max_quads) = self.limits.max_quads {
+            if self.executed_quads >= max_quads {
+                return Err(self.limit_error(
+                    ErrorKind::QuadLimit,
+                    format!("Stopped after running {} quads", max_quads),
+                ));
+            }
+        }
+        if let Some(deadline) = self.limits.deadline {
+            if self.started.elapsed() > deadline {
+                return Err(self.limit_error(
+                    ErrorKind::Deadline,
+                    format!("Stopped after running for more than {:?}", deadline),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Writes a value to an address of the same type, in the current frame
+    // when it's a local or a temp
+    fn write(&mut self, address: i32, value: Value) -> Result<(), RuntimeError> {
+        let var_type = self.value_table.get_var_type(address);
+        if var_type != value.value_type() || self.value_table.is_constant(address) {
+            return Err(self.error(format!(
+                "A {} value can't be written to address {}",
+                type_name(&value.value_type()),
+                address
+            )));
+        }
+        if self.history.is_some() {
+            self.change = Some(Change::Write {
+                address,
+                previous: self.get_value(address),
+                upcoming: false,
+            });
+        }
+        self.value_table
+            .set_value(address, value.clone(), self.memory_stack.top_mut());
+        self.last_write = Some((address, value));
+        Ok(())
+    }
+
+    // Object files and assembly listings can call ids no function has
+    fn function(&self, id: i32) -> Result<(&'a String, &'a FunctionInfo), RuntimeError> {
+        let program = self.program;
+        program
+            .function_ids
+            .get(&id)
+            .and_then(|name| Some((name, program.functions.get(name)?)))
+            .ok_or_else(|| self.error(format!("There's no function with id {}", id)))
+    }
+
+    fn condition(&self, address: i32) -> Result<bool, RuntimeError> {
+        match self.get_value(address) {
+            Value::Bool(value) => Ok(value),
+            value => Err(self.error(format!(
+                "The condition must be a bool, found {}",
+                type_name(&value.value_type())
+            ))),
+        }
+    }
+
+    /// Runs the program from its first quad with fresh memory.
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        if self.program.quadruplets.is_empty() {
+            return Err(self.error("There's no quadruplets".to_string()));
+        }
+
+        self.reset();
+        self.resume()
+    }
+
+    /// Runs from the current quad until the program ends, keeping the memory,
+    /// as after `restore`.
+    pub fn resume(&mut self) -> Result<(), RuntimeError> {
+        while !self.is_finished() {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.instruction_pointer >= self.program.quadruplets.len()
+    }
+
+    /// Runs the quad at the instruction pointer.
+    pub fn step(&mut self) -> Result<(), RuntimeError> {
+        let instruction_pointer = self.instruction_pointer;
+        self.last_write = None;
+        self.check_limits()?;
+        self.execute()?;
+        self.executed_quads += 1;
+        if let Some(history) = &mut self.history {
+            history.push(Entry {
+                instruction_pointer,
+                change: self.change.take(),
+            });
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self) -> Result<(), RuntimeError> {
+        let program = self.program;
+        let quad = match program.quadruplets.get(self.instruction_pointer) {
+            Some(quad) => quad,
+            None => return Err(self.error("The program already ended".to_string())),
+        };
+        let op_code: QuadOperator = convert_quad_op_to_code(quad.operator);
+
+        match op_code {
+            QuadOperator::Add
+            | QuadOperator::Subtract
+            | QuadOperator::Multiply
+            | QuadOperator::Divide
+            | QuadOperator::GreaterThan
+            | QuadOperator::LessThan
+            | QuadOperator::NotEqual => {
+                let operator = convert_quad_op_to_semantic_op(op_code).unwrap();
+                let left = self.get_value(quad.arg1);
+                let right = self.get_value(quad.arg2.unwrap());
+                let result = self
+                    .cubo
+                    .evaluate(&left, operator, &right)
+                    .map_err(|message| self.error(message))?;
+                self.write(quad.result.unwrap(), result)?;
+            }
+            QuadOperator::GotoF => {
+                if !self.condition(quad.arg1)? {
+                    self.instruction_pointer = quad.arg2.unwrap();
+                    return Ok(());
+                }
+            }
+            QuadOperator::GotoV => {
+                if self.condition(quad.arg1)? {
+                    self.instruction_pointer = quad.arg2.unwrap();
+                    return Ok(());
+                }
+            }
+            QuadOperator::Goto => {
+                self.instruction_pointer = quad.arg1;
+                return Ok(());
+            }
+            QuadOperator::Assign => {
+                let value = self.get_value(quad.arg2.unwrap());
+                self.write(quad.arg1, value)?;
+            }
+            QuadOperator::Print => match self.value_table.get_var_type(quad.arg1) {
+                Type::String => {
+                    let text = self.value_table.get_string(quad.arg1);
+                    self.print(&text)?
+                }
+                Type::Int | Type::Float => match self.get_value(quad.arg1) {
+                    Value::Int(value) => self.print(&value)?,
+                    Value::Float(value) => self.print(&value)?,
+                    Value::Bool(value) => self.print(&value)?,
+                },
+                var_type => {
+                    return Err(
+                        self.error(format!("A {} value can't be printed", type_name(&var_type)))
+                    )
+                }
+            },
+            QuadOperator::Memory => {
+                let (function_name, function) = self.function(quad.arg1)?;
+                let memory = Memory::with_sizes(&function.vars_amount);
+                // A frame that was never called is replaced, not kept
+                let replaced = self.upcoming_function.as_ref().map_or(0, Memory::size);
+                let frame_memory = self.frame_memory - replaced + memory.size();
+                if self
+                    .limits
+                    .max_frame_memory
+                    .is_some_and(|max_frame_memory| frame_memory > max_frame_memory)
+                {
+                    return Err(self.limit_error(
+                        ErrorKind::MemoryLimit,
+                        format!(
+                            "Calling {} needs {} values of frame memory, more than the limit of {}",
+                            function_name,
+                            frame_memory,
+                            self.limits.max_frame_memory.unwrap()
+                        ),
+                    ));
+                }
+                let previous = self.upcoming_function.replace(memory);
+                if self.history.is_some() {
+                    self.change = Some(Change::Prepare { previous });
+                }
+                self.frame_memory = frame_memory;
+            }
+            QuadOperator::Param if quad.arg2.is_none() => {
+                self.builtin_args.push(self.get_value(quad.arg1));
+                if self.history.is_some() {
+                    self.change = Some(Change::Argument);
+                }
+            }
+            QuadOperator::CallBuiltin => {
+                // The arguments are only taken once the call worked, so the
+                // machine can resume from the state a failed call leaves
+                let arguments = &self.builtin_args;
+                let types: Vec<Type> = arguments.iter().map(Value::value_type).collect();
+                let builtin = match program.builtins.get(quad.arg1) {
+                    Some(builtin) if builtin.accepts(&types) => builtin,
+                    Some(builtin) => return Err(self.error(call_error(&builtin.name, &types))),
+                    None => return Err(self.error(format!("There's no builtin {}", quad.arg1))),
+                };
+                let random = self.random.state();
+                let result = match &builtin.function {
+                    BuiltinFunction::Native(function) => function(arguments),
+                    BuiltinFunction::Random(function) => function(&mut self.random, arguments),
+                };
+                // A failed call doesn't use up its random number either
+                let written = match result {
+                    Ok(result) => self.write(quad.result.unwrap(), result),
+                    Err(message) => Err(self.error(message)),
+                };
+                if let Err(error) = written {
+                    self.random = Random::new(random);
+                    return Err(error);
+                }
+                let arguments = std::mem::take(&mut self.builtin_args);
+                if let Some(Change::Write {
+                    address, previous, ..
+                }) = self.change.take()
+                {
+                    self.change = Some(Change::Builtin {
+                        arguments,
+                        address,
+                        previous,
+                        random,
+                    });
+                }
+            }
+            QuadOperator::Param => {
+                let var_value = self
+                    .value_table
+                    .get_value(quad.arg1, self.memory_stack.top());
+                if self.upcoming_function.is_none() {
+                    return Err(self.error("PARAM is used without a MEMORY quad".to_string()));
+                }
+                let param = quad.arg2.unwrap();
+                if self.value_table.get_var_type(param) != var_value.value_type()
+                    || self.value_table.is_constant(param)
+                {
+                    return Err(self.error(format!(
+                        "A {} value can't be written to address {}",
+                        type_name(&var_value.value_type()),
+                        param
+                    )));
+                }
+                if self.history.is_some() {
+                    self.change = Some(Change::Write {
+                        address: quad.arg2.unwrap(),
+                        previous: self
+                            .value_table
+                            .get_value(quad.arg2.unwrap(), self.upcoming_function.as_ref()),
+                        upcoming: true,
+                    });
+                }
+                self.value_table.set_value(
+                    quad.arg2.unwrap(),
+                    var_value.clone(),
+                    self.upcoming_function.as_mut(),
+                );
+                self.last_write = Some((quad.arg2.unwrap(), var_value));
+            }
+            QuadOperator::GoSub => {
+                let (function_name, function) = self.function(quad.arg1)?;
+                if self
+                    .limits
+                    .max_call_depth
+                    .is_some_and(|max_call_depth| self.call_depth() >= max_call_depth)
+                {
+                    return Err(self.limit_error(
+                        ErrorKind::CallDepthLimit,
+                        format!(
+                            "Calling {} goes past the call depth limit of {}",
+                            function_name,
+                            self.limits.max_call_depth.unwrap()
+                        ),
+                    ));
+                }
+                let memory = match self.upcoming_function.take() {
+                    Some(memory) => memory,
+                    None => {
+                        return Err(self
+                            .error(format!("{} is called without a MEMORY quad", function_name)))
+                    }
+                };
+                self.memory_stack.push(memory);
+                if self.history.is_some() {
+                    self.change = Some(Change::Call);
+                }
+
+                self.position_before_fcall
+                    .push(self.instruction_pointer + 1);
+                self.instruction_pointer = function.start_address;
+                return Ok(());
+            }
+            QuadOperator::EndFunc => {
+                let (frame, return_address) =
+                    match (self.memory_stack.pop(), self.position_before_fcall.pop()) {
+                        (Some(frame), Some(return_address)) => (frame, return_address),
+                        _ => return Err(self.error("ENDFUNC outside of a function".to_string())),
+                    };
+                self.frame_memory -= frame.size();
+                if self.history.is_some() {
+                    self.change = Some(Change::Return {
+                        frame,
+                        return_address,
+                    });
+                }
+                self.instruction_pointer = return_address;
+                return Ok(());
+            }
+            QuadOperator::EndProgram => {
+                self.print(&"System exited with a value 0")?;
+            }
+        }
+        self.instruction_pointer += 1;
+        Ok(())
+    }
+}
+
+// Length of each list of values
+fn shape(memory: &Memory) -> Vec<usize> {
+    memory.values.iter().flatten().map(Vec::len).collect()
+}
@@ -1,4 +1,33 @@
-use pandora::{Lexer, ProgramManager, ProgramParser};
+// The first five tests keep their original assertions
+#![allow(clippy::bool_assert_comparison)]
+
+use pandora::compiler::assembler::assemble;
+use pandora::compiler::builtins::BuiltinRegistry;
+use pandora::compiler::compiled_program::FORMAT_VERSION;
+use pandora::compiler::diagnostics::Diagnostic;
+use pandora::compiler::disassembler::disassemble;
+use pandora::compiler::memory_layout::{MemoryLayout, Scope, Segment};
+use pandora::compiler::quadruplets::{QuadOperator, Quadruplet, QuadrupletList};
+use pandora::compiler::semantic_cube::Type;
+use pandora::compiler::semantic_tables::{FunctionInfo, VarValue};
+use pandora::compiler::value_table::{ConstValue, ConstantPool, Value};
+use pandora::optimizer::cfg::build_graphs;
+use pandora::optimizer::cse::eliminate_common_subexpressions;
+use pandora::optimizer::dead_code::eliminate_dead_code;
+use pandora::optimizer::folding::fold_constants;
+use pandora::optimizer::licm::{find_loops, hoist_loop_invariants};
+use pandora::optimizer::temps::recycle_temps;
+use pandora::vm::debugger::{Breakpoint, Debugger, Stop};
+use pandora::vm::limits::Limits;
+use pandora::vm::profiler::Profiler;
+use pandora::vm::runtime_error::ErrorKind;
+use pandora::vm::snapshot::Snapshot;
+use pandora::vm::tracer::{TraceFormat, Tracer};
+use pandora::{
+    compile, compile_with_reports, run, CompileOptions, CompiledProgram, FunctionTable, HashMap,
+    Lexer, ProgramManager, ProgramParser, VirtualMachine,
+};
+use std::sync::{Arc, Mutex};
 
 fn normalize(s: &str) -> String {
     s.split_whitespace().collect::<Vec<_>>().join(" ")
@@ -68,3 +97,1741 @@ fn test_five() {
     let result = parser.parse(&mut manager, lexer);
     assert_eq!(result.is_ok(), true);
 }
+
+#[test]
+fn test_forward_calls() {
+    let mut source = std::fs::read_to_string("tests/test6.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let lexer = Lexer::new(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+
+    let pong_id = manager.function_id("pong").unwrap();
+    let ping_start = manager.tabla_funciones.get("ping").unwrap().start_address;
+    let calls_pong = (ping_start..manager.quadruplets.len()).any(|i| {
+        let quad = manager.quadruplets.get(i).unwrap();
+        quad.operator == 15 && quad.arg1 == pong_id
+    });
+    assert!(calls_pong);
+
+    VirtualMachine::new(&CompiledProgram::from_program(&manager))
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn test_undeclared_calls() {
+    let mut source = std::fs::read_to_string("tests/test7.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let lexer = Lexer::new(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+
+    let messages: Vec<String> = manager
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.clone())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Function second is not declared".to_string(),
+            "Function missing is not declared".to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_constants() {
+    let mut source = std::fs::read_to_string("tests/test8.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let lexer = Lexer::new(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+
+    let main_vars = &manager.tabla_funciones.get("main").unwrap().vars;
+    assert_eq!(main_vars.get("LIMIT").unwrap().value, VarValue::Int(10));
+    assert_eq!(main_vars.get("HALF").unwrap().value, VarValue::Float(0.5));
+    assert_eq!(main_vars.get("OFFSET").unwrap().value, VarValue::Int(2));
+
+    let scale_vars = &manager.tabla_funciones.get("scale").unwrap().vars;
+    assert_eq!(scale_vars.get("FACTOR").unwrap().value, VarValue::Int(5));
+
+    VirtualMachine::new(&CompiledProgram::from_program(&manager))
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn test_constant_errors() {
+    let mut source = std::fs::read_to_string("tests/test9.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let lexer = Lexer::new(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+
+    // Uses of BROKEN, WRONG and FLAG don't add errors of their own
+    let diagnostic = |message: &str, position| Diagnostic::new(message.to_string(), Some(position));
+    assert_eq!(
+        manager.diagnostics,
+        vec![
+            diagnostic("Division by zero", 63),
+            diagnostic(
+                "Constant WRONG is declared as Int but its value is Float",
+                71
+            ),
+            diagnostic("Constant ZERO is already declared", 95),
+            diagnostic(
+                "Constant FLAG is declared as Int but its value is Bool",
+                116
+            ),
+            diagnostic(
+                "a is not a constant and can't be evaluated at compile time",
+                216
+            ),
+            diagnostic("Cannot assign to constant ZERO", 247),
+        ]
+    );
+}
+
+#[test]
+fn test_constant_pool() {
+    let mut source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let lexer = Lexer::new(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+
+    let main_vars = &manager.tabla_funciones.get("main").unwrap().vars;
+    assert!(!main_vars.contains_key("1"));
+    assert!(!main_vars.contains_key("-1"));
+
+    let one = manager
+        .value_table
+        .constants
+        .address_of(&ConstValue::Int(1))
+        .unwrap();
+    let pool_len = manager.value_table.constants.len();
+    assert_eq!(manager.value_table.insert_cte_int(1).unwrap(), one);
+    manager
+        .value_table
+        .insert_cte_string("Fibonacci con valor de n: ".to_string())
+        .unwrap();
+    assert_eq!(manager.value_table.constants.len(), pool_len);
+
+    let zero = manager.value_table.insert_cte_float(0.0).unwrap();
+    let negative_zero = manager.value_table.insert_cte_float(-0.0).unwrap();
+    assert_ne!(zero, negative_zero);
+    assert_eq!(manager.value_table.insert_cte_float(0.0).unwrap(), zero);
+
+    let pool = &manager.value_table.constants;
+    let loaded = ConstantPool::from_text(&pool.to_text(), &manager.value_table.layout).unwrap();
+    assert_eq!(&loaded, pool);
+}
+
+fn small_layout() -> MemoryLayout {
+    MemoryLayout::new(vec![
+        Segment::new(Type::Int, Scope::Global, 100, 2),
+        Segment::new(Type::Int, Scope::Local, 200, 10),
+        Segment::new(Type::Int, Scope::Temp, 300, 1),
+        Segment::new(Type::Float, Scope::Global, 400, 10),
+        Segment::new(Type::Bool, Scope::Temp, 500, 10),
+        Segment::new(Type::Int, Scope::Constant, 600, 10),
+        Segment::new(Type::Float, Scope::Constant, 700, 10),
+        Segment::new(Type::String, Scope::Constant, 800, 10),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn test_memory_layout() {
+    let mut source = std::fs::read_to_string("tests/test10.pdra").expect("Unable to read file");
+    source = normalize(&source);
+
+    let lexer = Lexer::new(&source);
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::with_layout(small_layout());
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+
+    let messages: Vec<String> = manager
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.clone())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Out of memory for global int values: the segment holds 2 addresses".to_string(),
+            "Out of memory for temp int values: the segment holds 1 addresses".to_string(),
+        ]
+    );
+
+    let main_vars = &manager.tabla_funciones.get("main").unwrap().vars;
+    let c_address = main_vars.get("c").unwrap().address;
+    assert_eq!(manager.value_table.get_var_type(c_address), Type::Int);
+    assert_eq!(main_vars.get("x").unwrap().address, 400);
+
+    let lexer = Lexer::new(&source);
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, lexer);
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+    VirtualMachine::new(&CompiledProgram::from_program(&manager))
+        .run()
+        .unwrap();
+}
+
+#[test]
+fn test_overlapping_layout() {
+    let layout = MemoryLayout::new(vec![
+        Segment::new(Type::Int, Scope::Global, 1000, 1000),
+        Segment::new(Type::Float, Scope::Global, 1500, 1000),
+    ]);
+    assert!(layout.is_err());
+
+    let layout = MemoryLayout::default();
+    assert_eq!(layout.decode(5000).unwrap().scope, Scope::Local);
+    assert_eq!(layout.decode(5000).unwrap().var_type, Type::Float);
+    assert!(layout.decode(999).is_none());
+}
+
+// Runs a program on a fresh machine and reads main's variables at the end
+fn global_values(program: &CompiledProgram) -> Vec<(String, Value)> {
+    let mut machine = VirtualMachine::new(program);
+    machine.run().unwrap();
+    main_values(program, &machine)
+}
+
+fn main_values(program: &CompiledProgram, machine: &VirtualMachine) -> Vec<(String, Value)> {
+    let mut values: Vec<(String, Value)> = program
+        .functions
+        .get("main")
+        .unwrap()
+        .vars
+        .values()
+        .filter(|var| program.constants.get(var.address).is_none())
+        .map(|var| (var.name.clone(), machine.get_value(var.address)))
+        .collect();
+    values.sort_by(|a, b| a.0.cmp(&b.0));
+    values
+}
+
+#[test]
+fn test_recycle_temps() {
+    let mut source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let expected = global_values(&CompiledProgram::from_program(&manager));
+
+    let mut optimized = ProgramManager::new();
+    let result = parser.parse(&mut optimized, Lexer::new(&source));
+    assert!(result.is_ok());
+    let reports = recycle_temps(
+        &mut optimized.quadruplets,
+        &mut optimized.tabla_funciones,
+        &optimized.value_table.layout,
+    );
+
+    assert!(reports
+        .iter()
+        .all(|report| report.size_after() <= report.size_before()));
+    let factorial = reports
+        .iter()
+        .find(|report| report.function == "factorial")
+        .unwrap();
+    assert!(factorial.size_after() < factorial.size_before());
+    assert_eq!(
+        global_values(&CompiledProgram::from_program(&optimized)),
+        expected
+    );
+}
+
+#[test]
+fn test_fold_constants() {
+    let mut source = std::fs::read_to_string("tests/test11.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let expected = global_values(&CompiledProgram::from_program(&manager));
+
+    let mut optimized = ProgramManager::new();
+    let result = parser.parse(&mut optimized, Lexer::new(&source));
+    assert!(result.is_ok());
+    let removed = fold_constants(
+        &mut optimized.quadruplets,
+        &mut optimized.tabla_funciones,
+        &mut optimized.value_table,
+        &optimized.cubo,
+    );
+
+    assert_eq!(
+        optimized.quadruplets.len(),
+        manager.quadruplets.len() - removed as i32
+    );
+    // The literal -5 no longer needs a multiplication at runtime
+    let minus_one = optimized
+        .value_table
+        .constants
+        .address_of(&ConstValue::Int(-1))
+        .unwrap();
+    assert!(optimized
+        .quadruplets
+        .iter()
+        .all(|quad| !quad.reads().contains(&minus_one)));
+
+    let values = global_values(&CompiledProgram::from_program(&optimized));
+    assert_eq!(values, expected);
+    assert!(values.contains(&("a".to_string(), Value::Int(10))));
+    assert!(values.contains(&("c".to_string(), Value::Int(40))));
+    assert!(values.contains(&("e".to_string(), Value::Int(30))));
+    assert!(values.contains(&("x".to_string(), Value::Float(3.25))));
+}
+
+#[test]
+fn test_dead_code() {
+    let mut source = std::fs::read_to_string("tests/test12.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let expected = global_values(&CompiledProgram::from_program(&manager));
+
+    let mut optimized = ProgramManager::new();
+    let result = parser.parse(&mut optimized, Lexer::new(&source));
+    assert!(result.is_ok());
+    let removed = eliminate_dead_code(&mut optimized.quadruplets, &mut optimized.tabla_funciones);
+    assert!(removed > 0);
+    assert_eq!(
+        optimized.quadruplets.len(),
+        manager.quadruplets.len() - removed as i32
+    );
+
+    // No jump lands on another GOTO after the chains are collapsed
+    for quad in optimized.quadruplets.iter() {
+        if let Some(target) = quad.jump_target() {
+            let landing = optimized.quadruplets.get(target).unwrap();
+            assert_ne!(landing.op(), QuadOperator::Goto);
+        }
+    }
+
+    let values = global_values(&CompiledProgram::from_program(&optimized));
+    assert_eq!(values, expected);
+    assert!(values.contains(&("a".to_string(), Value::Int(111))));
+    assert!(values.contains(&("b".to_string(), Value::Int(2))));
+}
+
+#[test]
+fn test_unreachable_quads() {
+    let mut quadruplets = QuadrupletList::new();
+    quadruplets.push(Quadruplet::new(1, 3, None, None));
+    quadruplets.push(Quadruplet::new(12, 9000, None, None));
+    quadruplets.push(Quadruplet::new(1, 4, None, None));
+    quadruplets.push(Quadruplet::new(1, 2, None, None));
+    quadruplets.push(Quadruplet::new(12, 9001, None, None));
+    quadruplets.push(Quadruplet::new(17, 0, None, None));
+
+    let mut functions = FunctionTable::new();
+    functions.insert(
+        "main".to_string(),
+        FunctionInfo {
+            name: "main".to_string(),
+            params: vec![],
+            vars: HashMap::new(),
+            vars_amount: vec![vec![0, 0]; 3],
+            start_address: 3,
+        },
+    );
+
+    let removed = eliminate_dead_code(&mut quadruplets, &mut functions);
+    assert_eq!(removed, 4);
+    assert_eq!(quadruplets.len(), 2);
+    assert_eq!(quadruplets.get(0).unwrap().op(), QuadOperator::Print);
+    assert_eq!(quadruplets.get(0).unwrap().arg1, 9001);
+    assert_eq!(functions.get("main").unwrap().start_address, 0);
+}
+
+#[test]
+fn test_control_flow_graph() {
+    let mut source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+
+    let graphs = build_graphs(&manager.quadruplets, &manager.tabla_funciones);
+    assert_eq!(graphs.len(), manager.tabla_funciones.len());
+
+    // Entry, loop condition, loop body and exit
+    let fibonacci = graphs
+        .iter()
+        .find(|graph| graph.function == "fibonacci")
+        .unwrap();
+    assert_eq!(fibonacci.blocks.len(), 4);
+    assert_eq!(fibonacci.successors(0), vec![1]);
+    assert_eq!(fibonacci.successors(1).len(), 2);
+    assert_eq!(fibonacci.successors(2), vec![1]);
+    assert!(fibonacci.successors(3).is_empty());
+    assert_eq!(fibonacci.predecessors(1), vec![0, 2]);
+
+    // Every call ends a block of main
+    let main = graphs
+        .iter()
+        .find(|graph| graph.function == "main")
+        .unwrap();
+    let calls = manager
+        .quadruplets
+        .iter()
+        .filter(|quad| quad.op() == QuadOperator::GoSub)
+        .count();
+    assert!(main.blocks.len() > calls);
+
+    let dot = fibonacci.to_dot(&manager.quadruplets);
+    assert!(dot.starts_with("digraph \"fibonacci\" {"));
+    assert!(dot.contains("B1 -> B3 [label=\"false\"];"));
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn test_common_subexpressions() {
+    let mut source = std::fs::read_to_string("tests/test13.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let expected = global_values(&CompiledProgram::from_program(&manager));
+
+    let mut optimized = ProgramManager::new();
+    let result = parser.parse(&mut optimized, Lexer::new(&source));
+    assert!(result.is_ok());
+    let removed = eliminate_common_subexpressions(
+        &mut optimized.quadruplets,
+        &mut optimized.tabla_funciones,
+        &optimized.value_table.layout,
+    );
+
+    // a * b and i + 1 in main, y * x and the printed x * y in square_sum
+    assert_eq!(removed, 4);
+    let count = |manager: &ProgramManager, op: QuadOperator| {
+        manager
+            .quadruplets
+            .iter()
+            .filter(|quad| quad.op() == op)
+            .count()
+    };
+    assert_eq!(count(&optimized, QuadOperator::Multiply), 2);
+    assert_eq!(count(&optimized, QuadOperator::Add), 4);
+
+    let values = global_values(&CompiledProgram::from_program(&optimized));
+    assert_eq!(values, expected);
+    assert!(values.contains(&("c".to_string(), Value::Int(24))));
+    assert!(values.contains(&("e".to_string(), Value::Int(2))));
+    assert!(values.contains(&("f".to_string(), Value::Int(11))));
+}
+
+#[test]
+fn test_loop_invariants() {
+    let mut source = std::fs::read_to_string("tests/test14.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let expected = global_values(&CompiledProgram::from_program(&manager));
+
+    let mut optimized = ProgramManager::new();
+    let result = parser.parse(&mut optimized, Lexer::new(&source));
+    assert!(result.is_ok());
+    let moved = hoist_loop_invariants(
+        &mut optimized.quadruplets,
+        &mut optimized.tabla_funciones,
+        &optimized.value_table.layout,
+    );
+    assert_eq!(optimized.quadruplets.len(), manager.quadruplets.len());
+
+    // Int arithmetic could overflow, so only the n * 2, n + 1 and k * 3 of
+    // the conditions leave their loops. The n + 1 of the inner loop stays in
+    // the outer one, where it's no longer part of a condition.
+    assert_eq!(moved, 3);
+
+    // Both a * b stay, and the loop that calls grow keeps a * 10
+    let multiplications: Vec<usize> =
+        find_loops(&optimized.quadruplets, 0..optimized.quadruplets.len())
+            .into_iter()
+            .map(|body| {
+                body.filter(|i| {
+                    optimized.quadruplets.get(*i).unwrap().op() == QuadOperator::Multiply
+                })
+                .count()
+            })
+            .collect();
+    assert_eq!(multiplications, vec![0, 1, 1, 2]);
+
+    let values = global_values(&CompiledProgram::from_program(&optimized));
+    assert_eq!(values, expected);
+    assert!(values.contains(&("c".to_string(), Value::Int(60))));
+    assert!(values.contains(&("a".to_string(), Value::Int(8))));
+}
+
+#[test]
+fn test_loop_invariants_overflow() {
+    let mut source = std::fs::read_to_string("tests/test23.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let expected = global_values(&CompiledProgram::from_program(&manager));
+
+    // big + 1 is never run, since the first loop doesn't iterate and the if
+    // of the second one isn't taken. Only the float x * 2.0 leaves its loop.
+    let mut optimized = ProgramManager::new();
+    let result = parser.parse(&mut optimized, Lexer::new(&source));
+    assert!(result.is_ok());
+    let moved = hoist_loop_invariants(
+        &mut optimized.quadruplets,
+        &mut optimized.tabla_funciones,
+        &optimized.value_table.layout,
+    );
+    assert_eq!(moved, 1);
+
+    let values = global_values(&CompiledProgram::from_program(&optimized));
+    assert_eq!(values, expected);
+    assert!(values.contains(&("y".to_string(), Value::Float(9.0))));
+}
+
+#[test]
+fn test_object_file() {
+    let mut source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let object = CompiledProgram::from_program(&manager);
+    let expected = global_values(&object);
+
+    let text = object.to_text();
+    let from_text = CompiledProgram::load(text.as_bytes()).unwrap();
+    assert_eq!(from_text.to_text(), text);
+    assert_eq!(from_text.functions, object.functions);
+
+    let bytes = object.to_bytes();
+    assert!(bytes.len() < text.len());
+    let from_bytes = CompiledProgram::load(&bytes).unwrap();
+    assert_eq!(from_bytes.to_bytes(), bytes);
+    assert_eq!(from_bytes.to_text(), text);
+
+    for loaded in [from_text, from_bytes] {
+        assert_eq!(global_values(&loaded), expected);
+    }
+
+    let newer = text.replacen(
+        &format!("pandora-object {}", FORMAT_VERSION),
+        &format!("pandora-object {}", FORMAT_VERSION + 1),
+        1,
+    );
+    assert!(CompiledProgram::load(newer.as_bytes())
+        .unwrap_err()
+        .contains("not compatible"));
+    let mut newer = bytes.clone();
+    newer[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    assert!(CompiledProgram::load(&newer)
+        .unwrap_err()
+        .contains("not compatible"));
+    assert!(CompiledProgram::load(&bytes[..bytes.len() - 3]).is_err());
+
+    // Version 1 files called builtins by their position in the registry,
+    // which changes as builtins are added
+    let older = text.replacen(
+        &format!("pandora-object {}", FORMAT_VERSION),
+        "pandora-object 1",
+        1,
+    );
+    assert!(CompiledProgram::load(older.as_bytes())
+        .unwrap_err()
+        .contains("not compatible"));
+    let mut older = bytes.clone();
+    older[4..8].copy_from_slice(&1u32.to_le_bytes());
+    assert!(CompiledProgram::load(&older)
+        .unwrap_err()
+        .contains("not compatible"));
+
+    // Corrupt files are rejected before the machine runs them
+    let corrupt = |from: &str, to: &str| {
+        CompiledProgram::load(text.replacen(from, to, 1).as_bytes()).unwrap_err()
+    };
+    assert_eq!(
+        corrupt("quad GOTO 38", "quad GOTO 900"),
+        "Quad 0: jumps to 900, outside the program"
+    );
+    assert_eq!(
+        corrupt("quad TIMES 5000 5000 6000", "quad TIMES 5000 5000 _"),
+        "Quad 2: missing its result"
+    );
+    assert_eq!(
+        corrupt("quad PRINT 6001", "quad PRINT 100"),
+        "Quad 4: address 100 is not in any segment"
+    );
+    assert_eq!(
+        corrupt("quad PRINT 6001", "quad PRINT 6500"),
+        "Quad 4: address 6500 is outside its memory"
+    );
+    assert!(corrupt("function main - 38", "function main - 900").contains("outside the program"));
+    assert!(corrupt("quad MEMORY 1", "quad PRINT 1").contains("not in any segment"));
+    assert!(corrupt("globals 8", "globals 5000").contains("don't fit"));
+    let mut negative = object.clone();
+    negative.globals[0][0] = -1;
+    assert!(CompiledProgram::load(&negative.to_bytes())
+        .unwrap_err()
+        .contains("don't fit"));
+}
+
+#[test]
+fn test_disassembler() {
+    let mut source = std::fs::read_to_string("tests/test8.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+
+    let text = disassemble(&CompiledProgram::from_program(&manager));
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.split(';').next().unwrap().trim())
+        .collect();
+
+    assert_eq!(lines[0], "GOTO main");
+    assert!(lines.contains(&"function scale id 0 frame 2 1 0 0 0 0"));
+    assert!(lines.contains(&"param n int"));
+    assert!(lines.contains(&"const FACTOR int 5"));
+    assert!(lines.contains(&"var result int @2001"));
+    assert!(lines.contains(&"TIMES n, 5 -> t1"));
+    assert!(lines.contains(&"PRINT \"Escalado: \""));
+
+    // Jump targets become labels inside main
+    assert!(lines.contains(&"LT i, 10 -> t1"));
+    assert!(lines.contains(&"GOTOF t1, L2"));
+    assert!(lines.contains(&"GOTO L1"));
+    assert!(lines.contains(&"L2:"));
+    assert!(lines.contains(&"MEMORY scale"));
+    assert!(lines.contains(&"PARAM 10 -> n"));
+    assert_eq!(lines.iter().filter(|line| **line == "end").count(), 2);
+}
+
+#[test]
+fn test_assembler_round_trip() {
+    for file in ["tests/test1.pdra", "tests/test8.pdra"] {
+        let mut source = std::fs::read_to_string(file).expect("Unable to read file");
+        source = normalize(&source);
+        let parser = ProgramParser::new();
+
+        let mut manager = ProgramManager::new();
+        let result = parser.parse(&mut manager, Lexer::new(&source));
+        assert!(result.is_ok());
+        let object = CompiledProgram::from_program(&manager);
+        let expected = global_values(&object);
+
+        let text = disassemble(&object);
+        let assembled = assemble(&text).unwrap();
+        assert_eq!(disassemble(&assembled), text);
+        // Literals may land on other addresses of the pool, the rest is kept
+        for (name, function) in &object.functions {
+            let assembled = assembled.functions.get(name).unwrap();
+            assert_eq!(assembled.start_address, function.start_address);
+            assert_eq!(assembled.vars_amount, function.vars_amount);
+            assert_eq!(assembled.params, function.params);
+        }
+        assert_eq!(assembled.function_ids, object.function_ids);
+
+        assert_eq!(global_values(&assembled), expected);
+    }
+}
+
+#[test]
+fn test_assembler() {
+    let text = std::fs::read_to_string("tests/test15.pdasm").expect("Unable to read file");
+    let object = assemble(&text).unwrap();
+    let main = object.functions.get("main").unwrap();
+    assert_eq!(main.start_address, 1);
+    assert_eq!(main.vars_amount, vec![vec![2, 1], vec![0, 0], vec![0, 1]]);
+    assert_eq!(object.quadruplets.get(7).unwrap().op(), QuadOperator::GotoV);
+
+    assert!(global_values(&object).contains(&("total".to_string(), Value::Int(15))));
+}
+
+#[test]
+fn test_assembler_errors() {
+    let program = |body: &str| {
+        format!(
+            "function main\n var i int\n var x float\n temp t1 int\n temp t2 bool\nmain:\n{}\n ENDPROGRAM\nend\n",
+            body
+        )
+    };
+    let error = |body: &str| assemble(&program(body)).unwrap_err();
+
+    assert!(assemble(&program(" SUM i, 1 -> t1")).is_ok());
+    assert_eq!(
+        error(" SUM i, x -> t1"),
+        "line 7: The result of SUM is a float value"
+    );
+    assert_eq!(
+        error(" GOTOF i, main"),
+        "line 7: The condition of GOTOF must be a bool"
+    );
+    assert_eq!(
+        error(" ASSIGN i, x"),
+        "line 7: Types must be the same to assign values"
+    );
+    assert_eq!(
+        error(" ASSIGN 5, i"),
+        "line 7: Address 9000 can't be written"
+    );
+    assert_eq!(
+        error(" SUM t2, 1 -> t1"),
+        "line 7: SUM can't be applied to bool and int"
+    );
+    assert_eq!(error(" GOTO nowhere"), "line 7: Unknown label nowhere");
+    assert!(assemble(&program(" GOTO @2")).is_ok());
+    assert_eq!(
+        error(" GOTO @-1"),
+        "line 7: Jump target -1 is outside the program"
+    );
+    assert_eq!(
+        error(" GOTOF t2, @3"),
+        "line 7: Jump target 3 is outside the program"
+    );
+    assert_eq!(error(" PRINT y"), "line 7: Unknown operand y");
+    assert_eq!(
+        error(" SUM i 1 -> t1"),
+        "line 7: Operands must be separated by commas"
+    );
+    assert_eq!(
+        assemble("function main\n var i int @4000\nend\n").unwrap_err(),
+        "line 2: Address 4000 is not in the global int segment"
+    );
+}
+
+#[test]
+fn test_virtual_machine() {
+    let mut source = std::fs::read_to_string("tests/test8.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let program = CompiledProgram::from_program(&manager);
+    let expected = global_values(&program);
+
+    // Running again on the same machine starts over with fresh memory
+    let mut machine = VirtualMachine::new(&program);
+    machine.run().unwrap();
+    let first: Vec<Value> = program.functions["main"]
+        .vars
+        .values()
+        .map(|var| machine.get_value(var.address))
+        .collect();
+    machine.run().unwrap();
+    let second: Vec<Value> = program.functions["main"]
+        .vars
+        .values()
+        .map(|var| machine.get_value(var.address))
+        .collect();
+    assert_eq!(first, second);
+
+    let runs: Vec<Vec<(String, Value)>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| global_values(&program)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    assert!(runs.iter().all(|values| *values == expected));
+}
+
+#[test]
+fn test_runtime_errors() {
+    let mut source = std::fs::read_to_string("tests/test16.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+    let program = CompiledProgram::from_program(&manager);
+
+    let mut machine = VirtualMachine::new(&program);
+    let error = machine.run().unwrap_err();
+    assert_eq!(error.message, "Division by zero");
+    assert_eq!(
+        program
+            .quadruplets
+            .get(error.instruction_pointer)
+            .unwrap()
+            .op(),
+        QuadOperator::Divide
+    );
+
+    // Mixed int and float comparisons go through the same operations
+    let main_vars = &program.functions["main"].vars;
+    assert_eq!(machine.get_value(main_vars["b"].address), Value::Int(1));
+    assert_eq!(machine.get_value(main_vars["c"].address), Value::Int(5));
+
+    // Operands the semantic cube doesn't accept stop the machine. The
+    // assembler rejects them, so the quad is patched in afterwards.
+    let mut program = assemble(
+        "    GOTO main\nfunction main\n    temp t1 int\n    temp t2 bool\nmain:\n    LT 1, 2 -> t2\n    SUM 1, 2 -> t1\n    ENDPROGRAM\nend\n",
+    )
+    .unwrap();
+    let flag = program.quadruplets.get(1).unwrap().result.unwrap();
+    let sum = program.quadruplets.get(2).unwrap().result;
+    let quad = Quadruplet::new(QuadOperator::Add.code(), flag, Some(flag), sum);
+    program.quadruplets.set(2, quad);
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.instruction_pointer, 2);
+    assert_eq!(error.message, "Invalid operation between Bool and Bool");
+}
+
+#[test]
+fn test_invalid_programs() {
+    let program = assemble("").unwrap();
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.message, "There's no quadruplets");
+
+    // Quads patched in after assembling, as an object file could hold them
+    let text = "    GOTO main\nfunction f id 0\nf:\n    ENDFUNC\nend\nfunction main\nmain:\n    MEMORY f\n    GOSUB f\n    ENDPROGRAM\nend\n";
+    let mut program = assemble(text).unwrap();
+    program.quadruplets.set(
+        2,
+        Quadruplet::new(QuadOperator::Memory.code(), 7, None, None),
+    );
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.instruction_pointer, 2);
+    assert_eq!(error.message, "There's no function with id 7");
+
+    let mut program = assemble(text).unwrap();
+    program.quadruplets.set(
+        2,
+        Quadruplet::new(QuadOperator::GoSub.code(), 0, None, None),
+    );
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.message, "f is called without a MEMORY quad");
+
+    let mut program = assemble(text).unwrap();
+    program.quadruplets.set(
+        2,
+        Quadruplet::new(QuadOperator::EndFunc.code(), 0, None, None),
+    );
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.message, "ENDFUNC outside of a function");
+
+    let text = "    GOTO main\nfunction f id 0\n    param n int\nf:\n    ENDFUNC\nend\nfunction main\n    var a int\n    var b float\nmain:\n    MEMORY f\n    PARAM a -> n\n    GOSUB f\n    ENDPROGRAM\nend\n";
+    let mut program = assemble(text).unwrap();
+    let a = program.functions["main"].vars["a"].address;
+    let b = program.functions["main"].vars["b"].address;
+    let n = program.functions["f"].vars["n"].address;
+    program.quadruplets.set(
+        2,
+        Quadruplet::new(QuadOperator::Print.code(), a, None, None),
+    );
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.instruction_pointer, 3);
+    assert_eq!(error.message, "PARAM is used without a MEMORY quad");
+
+    let mut program = assemble(text).unwrap();
+    program.quadruplets.set(
+        3,
+        Quadruplet::new(QuadOperator::Param.code(), b, Some(n), None),
+    );
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(
+        error.message,
+        format!("A float value can't be written to address {}", n)
+    );
+}
+
+#[test]
+fn test_debugger() {
+    // Parsed as it is, so quads keep the lines of the source
+    let source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let program = CompiledProgram::from_program(&manager);
+    let mut debugger = Debugger::with_source(&program, &source);
+    let functions = |debugger: &Debugger| -> Vec<String> {
+        debugger
+            .call_stack()
+            .into_iter()
+            .map(|frame| frame.function)
+            .collect()
+    };
+    let current_op = |debugger: &Debugger| {
+        program
+            .quadruplets
+            .get(debugger.instruction_pointer())
+            .unwrap()
+            .op()
+    };
+
+    assert_eq!(
+        debugger.add_breakpoint(Breakpoint::Line(4)),
+        Err("Line 4 has no code".to_string())
+    );
+    debugger.add_breakpoint(Breakpoint::Line(38)).unwrap();
+    assert!(matches!(debugger.resume(), Ok(Stop::Breakpoint(_))));
+    assert_eq!(debugger.line(), Some(38));
+    assert_eq!(functions(&debugger), vec!["factorial", "main"]);
+    assert_eq!(debugger.variable("max_num_fact"), Some(Value::Int(13)));
+    assert_eq!(debugger.variable("fact"), Some(Value::Int(1)));
+
+    // The recursive call stops again one frame deeper
+    assert!(matches!(debugger.resume(), Ok(Stop::Breakpoint(_))));
+    assert_eq!(functions(&debugger), vec!["factorial", "factorial", "main"]);
+    assert_eq!(debugger.variable("n"), Some(Value::Int(2)));
+
+    debugger.remove_breakpoint(Breakpoint::Line(38)).unwrap();
+    assert!(matches!(debugger.step_out(), Ok(Stop::Paused(_))));
+    assert_eq!(functions(&debugger), vec!["factorial", "main"]);
+
+    debugger.add_breakpoint(Breakpoint::Line(51)).unwrap();
+    assert!(matches!(debugger.resume(), Ok(Stop::Breakpoint(_))));
+    assert_eq!(functions(&debugger), vec!["main"]);
+    assert_eq!(debugger.variable("fact"), Some(Value::Int(6227020800)));
+    while current_op(&debugger) != QuadOperator::GoSub {
+        debugger.step_over().unwrap();
+    }
+    debugger.step_into().unwrap();
+    assert_eq!(functions(&debugger), vec!["fibonacci", "main"]);
+    assert_eq!(debugger.variable("n"), Some(Value::Int(20)));
+    debugger.step_out().unwrap();
+    assert_eq!(debugger.line(), Some(52));
+
+    while current_op(&debugger) != QuadOperator::GoSub {
+        debugger.step_over().unwrap();
+    }
+    assert!(matches!(debugger.step_over(), Ok(Stop::Paused(_))));
+    assert_eq!(functions(&debugger), vec!["main"]);
+    assert_eq!(debugger.line(), Some(53));
+    assert_eq!(debugger.resume(), Ok(Stop::Finished));
+}
+
+#[test]
+fn test_tracer() {
+    let mut source = std::fs::read_to_string("tests/test8.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let program = CompiledProgram::from_program(&manager);
+
+    let mut tracer = Tracer::new(&program, vec![], TraceFormat::Text);
+    tracer.run().unwrap();
+    let trace = String::from_utf8(tracer.into_sink()).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines[0], "-> main");
+    assert_eq!(lines[lines.len() - 1], "<- main");
+    assert!(lines.contains(&"-> scale"));
+    assert!(lines.contains(&"<- scale"));
+    assert!(lines
+        .iter()
+        .any(|line| line.ends_with("main: ASSIGN i, 0  ; i <- 0")));
+    assert!(lines
+        .iter()
+        .any(|line| line.ends_with("scale: TIMES n, 5 -> t1  ; t1 = 50")));
+    assert!(lines
+        .iter()
+        .any(|line| line.ends_with("main: PARAM 10 -> n  ; n <- 10")));
+    // The loop condition runs once more than its body
+    let conditions = lines
+        .iter()
+        .filter(|line| line.ends_with("main: LT i, 10 -> t1  ; t1 = true"))
+        .count();
+    assert_eq!(conditions, 10);
+
+    let mut tracer = Tracer::new(&program, vec![], TraceFormat::JsonLines).with_filter(&["scale"]);
+    tracer.run().unwrap();
+    let trace = String::from_utf8(tracer.into_sink()).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines[0], "{\"event\":\"enter\",\"function\":\"scale\"}");
+    assert_eq!(
+        lines[lines.len() - 1],
+        "{\"event\":\"exit\",\"function\":\"scale\"}"
+    );
+    assert!(lines.contains(
+        &"{\"event\":\"quad\",\"ip\":2,\"function\":\"scale\",\"instruction\":\"ASSIGN result, t1\",\"target\":\"result\",\"value\":50}"
+    ));
+    assert!(lines
+        .iter()
+        .all(|line| line.contains("\"function\":\"scale\"")));
+}
+
+#[test]
+fn test_profiler() {
+    let source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let program = CompiledProgram::from_program(&manager);
+
+    let profile = Profiler::with_source(&program, &source).run().unwrap();
+    let total: u64 = profile.quads.iter().sum();
+    let factorial = &profile.functions["factorial"];
+    assert_eq!(factorial.calls, 14);
+    assert_eq!(factorial.frames, 14);
+    assert_eq!(profile.functions["fibonacci"].calls, 2);
+    assert_eq!(profile.opcodes[&QuadOperator::GoSub], 18);
+    assert_eq!(profile.opcodes[&QuadOperator::EndProgram], 1);
+    assert_eq!(
+        profile.functions.values().map(|f| f.quads).sum::<u64>(),
+        total
+    );
+    // Only the jump to main has no line
+    assert_eq!(profile.lines.values().sum::<u64>(), total - 1);
+    // `fact = fact * n` is a TIMES and an ASSIGN, run once per call but the last
+    assert_eq!(profile.lines[&38], 26);
+
+    let folded = profile.folded();
+    assert!(folded
+        .lines()
+        .any(|line| line.starts_with("main;pelos;fibonacci ")));
+    let folded_total: u64 = folded
+        .lines()
+        .map(|line| line.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(folded_total, total);
+
+    let table = profile.table();
+    let functions: Vec<&str> = table
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect();
+    assert_eq!(functions[0], "fibonacci");
+    assert_eq!(functions.len(), 5);
+}
+
+#[test]
+fn test_limits() {
+    let parser = ProgramParser::new();
+    let mut programs = vec![];
+    for path in ["tests/test17.pdra", "tests/test18.pdra"] {
+        let mut source = std::fs::read_to_string(path).expect("Unable to read file");
+        source = normalize(&source);
+        let mut manager = ProgramManager::new();
+        let result = parser.parse(&mut manager, Lexer::new(&source));
+        assert!(result.is_ok());
+        assert!(manager.diagnostics.is_empty());
+        programs.push(CompiledProgram::from_program(&manager));
+    }
+    let (endless_loop, endless_recursion) = (&programs[0], &programs[1]);
+
+    let limits = Limits {
+        max_quads: Some(1000),
+        ..Limits::default()
+    };
+    let mut machine = VirtualMachine::with_limits(endless_loop, limits.clone());
+    let error = machine.run().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::QuadLimit);
+    assert_eq!(error.message, "Stopped after running 1000 quads in main");
+    assert_eq!(machine.executed_quads(), 1000);
+    assert_eq!(machine.instruction_pointer(), error.instruction_pointer);
+
+    let limits = Limits {
+        deadline: Some(std::time::Duration::from_millis(20)),
+        ..Limits::default()
+    };
+    let error = VirtualMachine::with_limits(endless_loop, limits)
+        .run()
+        .unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Deadline);
+
+    let limits = Limits {
+        max_call_depth: Some(100),
+        ..Limits::default()
+    };
+    let mut machine = VirtualMachine::with_limits(endless_recursion, limits);
+    let error = machine.run().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::CallDepthLimit);
+    assert_eq!(
+        error.message,
+        "Calling forever goes past the call depth limit of 100 in forever"
+    );
+    assert_eq!(machine.call_depth(), 100);
+    let calls = endless_recursion.functions["main"].vars["calls"].address;
+    assert_eq!(machine.get_value(calls), Value::Int(100));
+
+    let frame = endless_recursion.functions["forever"]
+        .vars_amount
+        .iter()
+        .flatten()
+        .sum::<i32>() as usize;
+    let limits = Limits {
+        max_frame_memory: Some(frame * 10),
+        ..Limits::default()
+    };
+    let mut machine = VirtualMachine::with_limits(endless_recursion, limits);
+    let error = machine.run().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::MemoryLimit);
+    assert_eq!(machine.call_depth(), 10);
+
+    // Limits a program stays within don't change how it runs
+    let limits = Limits {
+        max_quads: Some(1000),
+        max_call_depth: Some(10),
+        max_frame_memory: Some(1000),
+        deadline: Some(std::time::Duration::from_secs(10)),
+    };
+    let mut source = std::fs::read_to_string("tests/test8.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let mut manager = ProgramManager::new();
+    parser.parse(&mut manager, Lexer::new(&source)).unwrap();
+    let program = CompiledProgram::from_program(&manager);
+    assert!(VirtualMachine::with_limits(&program, limits).run().is_ok());
+}
+
+#[test]
+fn test_snapshots() {
+    let parser = ProgramParser::new();
+    let mut programs = vec![];
+    for path in ["tests/test1.pdra", "tests/test16.pdra"] {
+        let mut source = std::fs::read_to_string(path).expect("Unable to read file");
+        source = normalize(&source);
+        let mut manager = ProgramManager::new();
+        let result = parser.parse(&mut manager, Lexer::new(&source));
+        assert!(result.is_ok());
+        programs.push(CompiledProgram::from_program(&manager));
+    }
+    let (program, failing) = (&programs[0], &programs[1]);
+
+    let mut machine = VirtualMachine::new(program);
+    machine.run().unwrap();
+    let expected = main_values(program, &machine);
+    let total = machine.executed_quads();
+
+    // Inside the recursion of factorial, and right after a `MEMORY` whose
+    // frame hasn't been called yet
+    let deep = |machine: &VirtualMachine| machine.call_depth() == 3;
+    let calling = |machine: &VirtualMachine| {
+        let quad = machine.instruction_pointer() - 1;
+        quad >= 0 && program.quadruplets.get(quad).unwrap().op() == QuadOperator::Memory
+    };
+    for stop in [&deep as &dyn Fn(&VirtualMachine) -> bool, &calling] {
+        let mut machine = VirtualMachine::new(program);
+        while !stop(&machine) {
+            machine.step().unwrap();
+        }
+        let snapshot = machine.snapshot();
+        let text = snapshot.to_text();
+        assert_eq!(Snapshot::from_text(&text).unwrap(), snapshot);
+
+        let mut resumed = VirtualMachine::new(program);
+        resumed
+            .restore(&Snapshot::from_text(&text).unwrap())
+            .unwrap();
+        assert_eq!(resumed.call_stack(), machine.call_stack());
+        resumed.resume().unwrap();
+        assert_eq!(main_values(program, &resumed), expected);
+        assert_eq!(resumed.executed_quads(), total);
+    }
+
+    // A failed run can be looked at in the debugger
+    let mut machine = VirtualMachine::new(failing);
+    let error = machine.run().unwrap_err();
+    let snapshot = machine.snapshot();
+    let mut debugger = Debugger::new(failing);
+    debugger.restore(&snapshot).unwrap();
+    assert_eq!(debugger.instruction_pointer(), error.instruction_pointer);
+    assert_eq!(debugger.variable("c"), Some(Value::Int(5)));
+    assert_eq!(debugger.resume().unwrap_err(), error);
+
+    assert!(VirtualMachine::new(program).restore(&snapshot).is_err());
+    assert!(Snapshot::from_text("pandora-snapshot 2\nip 0\n").is_err());
+    assert!(Snapshot::from_text("pandora-snapshot 1\nip 0\nglobals - -\n").is_err());
+}
+
+#[test]
+fn test_history() {
+    let mut source = std::fs::read_to_string("tests/test1.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let program = CompiledProgram::from_program(&manager);
+
+    // Going back one quad at a time passes through every state of the run
+    let mut machine = VirtualMachine::new(&program);
+    machine.record_history(true);
+    let mut snapshots = vec![machine.snapshot()];
+    while !machine.is_finished() {
+        machine.step().unwrap();
+        snapshots.push(machine.snapshot());
+    }
+    assert_eq!(machine.history().len(), snapshots.len() - 1);
+    while let Some(snapshot) = snapshots.pop() {
+        assert_eq!(machine.snapshot(), snapshot);
+        machine.step_back();
+    }
+    assert!(!machine.step_back());
+
+    let expected = global_values(&program);
+    machine.resume().unwrap();
+    assert_eq!(main_values(&program, &machine), expected);
+    let total = machine.executed_quads();
+    machine.rewind(total / 2).unwrap();
+    assert_eq!(machine.executed_quads(), total / 2);
+    machine.resume().unwrap();
+    assert_eq!(main_values(&program, &machine), expected);
+
+    let mut debugger = Debugger::new(&program);
+    debugger.record_history(true);
+    let fibonacci = program.functions["fibonacci"].start_address;
+    debugger
+        .add_breakpoint(Breakpoint::Quad(fibonacci))
+        .unwrap();
+    assert_eq!(debugger.resume().unwrap(), Stop::Breakpoint(fibonacci));
+
+    // `temp` hasn't been written by this call yet, and `n` was written by the
+    // caller through `PARAM`
+    assert!(debugger.back_to_last_write("temp").is_err());
+    assert_eq!(debugger.instruction_pointer(), fibonacci);
+    debugger.back_to_last_write("n").unwrap();
+    let quad = program
+        .quadruplets
+        .get(debugger.instruction_pointer())
+        .unwrap();
+    assert_eq!(quad.op(), QuadOperator::Param);
+    assert_eq!(debugger.machine().call_depth(), 0);
+
+    // The last write of `fact` is 13! in the deepest call of factorial
+    debugger.back_to_last_write("fact").unwrap();
+    let quad = program
+        .quadruplets
+        .get(debugger.instruction_pointer())
+        .unwrap();
+    assert_eq!(quad.op(), QuadOperator::Assign);
+    assert_eq!(debugger.machine().current_function(), "factorial");
+    assert_eq!(debugger.variable("fact"), Some(Value::Int(479001600)));
+    debugger.step_into().unwrap();
+    assert_eq!(debugger.variable("fact"), Some(Value::Int(6227020800)));
+
+    debugger.rewind(0).unwrap();
+    assert_eq!(debugger.instruction_pointer(), 0);
+    assert!(debugger.step_back().is_err());
+}
+
+#[test]
+fn test_builtins() {
+    let mut source = std::fs::read_to_string("tests/test19.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+    let program = CompiledProgram::from_program(&manager);
+
+    // Domain errors of a builtin stop the machine at its `CALLBUILTIN`
+    let mut machine = VirtualMachine::new(&program);
+    machine.record_history(true);
+    let error = machine.run().unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Fault);
+    assert_eq!(error.message, "sqrt of a negative number: -2.0");
+    let quad = program.quadruplets.get(error.instruction_pointer).unwrap();
+    assert_eq!(quad.op(), QuadOperator::CallBuiltin);
+    assert_eq!(
+        main_values(&program, &machine),
+        vec![
+            ("i".to_string(), Value::Int(12)),
+            ("j".to_string(), Value::Int(4)),
+            ("k".to_string(), Value::Int(1024)),
+            ("x".to_string(), Value::Float(4.0)),
+            ("y".to_string(), Value::Float(2.0)),
+            ("z".to_string(), Value::Float(2.0)),
+        ]
+    );
+
+    // Arguments waiting for their builtin are part of the state, and a
+    // failed call leaves them there
+    let snapshot = machine.snapshot();
+    assert_eq!(snapshot.builtin_args, vec![Value::Float(-2.0)]);
+    assert_eq!(Snapshot::from_text(&snapshot.to_text()).unwrap(), snapshot);
+    machine.step_back();
+    assert!(machine.snapshot().builtin_args.is_empty());
+    machine.rewind(0).unwrap();
+    machine.restore(&snapshot).unwrap();
+    assert_eq!(machine.resume().unwrap_err(), error);
+
+    // Builtins keep their signature through the assembler
+    let text = disassemble(&program);
+    assert!(text.contains("CALLBUILTIN pow:float:float -> t"));
+    let assembled = assemble(&text).unwrap();
+    assert_eq!(disassemble(&assembled), text);
+    let mut machine = VirtualMachine::new(&assembled);
+    assert_eq!(machine.run().unwrap_err().message, error.message);
+
+    // Arguments are checked against the parameters of the builtin, ints
+    // taking the place of floats
+    let listing = |argument: &str| {
+        format!(
+            "function main\n var x float\n temp t1 bool\nmain:\n LT 1, 2 -> t1\n PARAM {}\n CALLBUILTIN sqrt:float -> x\n ENDPROGRAM\nend\n",
+            argument
+        )
+    };
+    assert!(assemble(&listing("4")).is_ok());
+    assert_eq!(
+        assemble(&listing("t1")).unwrap_err(),
+        "line 7: sqrt can't be called with (bool)"
+    );
+    let mut program = assemble(&listing("4.0")).unwrap();
+    let flag = program.quadruplets.get(0).unwrap().result.unwrap();
+    let quad = Quadruplet::new(QuadOperator::Param.code(), flag, None, None);
+    program.quadruplets.set(1, quad);
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.instruction_pointer, 2);
+    assert_eq!(error.message, "sqrt can't be called with (bool)");
+
+    let mut source = std::fs::read_to_string("tests/test20.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let messages: Vec<String> = manager
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.clone())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Function abs has the name of a builtin function".to_string(),
+            "sqrt can't be called with (float, float)".to_string(),
+            "pow can't be called with (bool, int)".to_string(),
+            "There's no builtin function nothing".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_host_functions() {
+    let mut source = std::fs::read_to_string("tests/test21.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+
+    let logged = Arc::new(Mutex::new(vec![]));
+    let log = Arc::clone(&logged);
+    let mut manager = ProgramManager::new();
+    manager
+        .register_function(
+            "get_setting",
+            vec![Type::Int],
+            Type::Float,
+            |args| match args[0] {
+                Value::Int(1) => Ok(Value::Float(1.5)),
+                Value::Int(2) => Ok(Value::Float(3.0)),
+                ref id => Err(format!("There's no setting {}", id)),
+            },
+        )
+        .unwrap();
+    manager
+        .register_function("log", vec![Type::Float], Type::Int, move |args| {
+            let mut log = log.lock().unwrap();
+            log.push(args[0].clone());
+            Ok(Value::Int(log.len() as i64))
+        })
+        .unwrap();
+    assert_eq!(
+        manager
+            .register_function("sqrt", vec![Type::Int], Type::Int, |args| Ok(
+                args[0].clone()
+            ))
+            .unwrap_err(),
+        "sqrt is already a builtin function"
+    );
+    assert!(manager
+        .register_function("name", vec![], Type::String, |args| Ok(args[0].clone()))
+        .is_err());
+
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+    let program = CompiledProgram::from_program(&manager);
+    assert_eq!(
+        global_values(&program),
+        vec![
+            ("level".to_string(), Value::Float(3.0)),
+            ("logged".to_string(), Value::Int(1)),
+            ("speed".to_string(), Value::Float(3.0)),
+        ]
+    );
+    assert_eq!(
+        *logged.lock().unwrap(),
+        vec![Value::Float(3.0), Value::Float(4.0)]
+    );
+
+    // Object files name the builtins they call, and loading finds them in the
+    // registry of the application, wherever it registered them
+    let text = program.to_text();
+    assert!(text.contains("builtin 13 get_setting:int\nbuiltin 14 log:float\n"));
+    let mut builtins = BuiltinRegistry::standard();
+    builtins
+        .register_host("log", vec![Type::Float], Type::Int, |_| Ok(Value::Int(9)))
+        .unwrap();
+    builtins
+        .register_host("get_setting", vec![Type::Int], Type::Float, |_| {
+            Ok(Value::Float(2.0))
+        })
+        .unwrap();
+    for data in [text.as_bytes().to_vec(), program.to_bytes()] {
+        let loaded = CompiledProgram::load_with_builtins(&data, &builtins).unwrap();
+        assert_eq!(
+            global_values(&loaded),
+            vec![
+                ("level".to_string(), Value::Float(2.0)),
+                ("logged".to_string(), Value::Int(9)),
+                ("speed".to_string(), Value::Float(4.0)),
+            ]
+        );
+        assert_eq!(
+            CompiledProgram::load(&data).unwrap_err(),
+            "There's no builtin get_setting:int"
+        );
+    }
+
+    // Calls are checked like calls to the program's own functions
+    let mut manager = ProgramManager::new();
+    manager
+        .register_function("get_setting", vec![Type::Float], Type::Float, |args| {
+            Ok(args[0].clone())
+        })
+        .unwrap();
+    manager
+        .register_function("log", vec![Type::Float, Type::Float], Type::Int, |_| {
+            Ok(Value::Int(0))
+        })
+        .unwrap();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let messages: Vec<String> = manager
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.clone())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Argument and parameters are not the same type on function get_setting".to_string(),
+            "Argument and parameters are not the same type on function get_setting".to_string(),
+            "Argument quantities doesn't match with function log declaration".to_string(),
+            "Argument quantities doesn't match with function log declaration".to_string(),
+        ]
+    );
+
+    // Errors of host functions stop the machine
+    let mut manager = ProgramManager::new();
+    manager
+        .register_function("get_setting", vec![Type::Int], Type::Float, |_| {
+            Err("Settings aren't loaded".to_string())
+        })
+        .unwrap();
+    manager
+        .register_function("log", vec![Type::Float], Type::Int, |_| Ok(Value::Int(0)))
+        .unwrap();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    let program = CompiledProgram::from_program(&manager);
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.message, "Settings aren't loaded");
+    assert_eq!(error.instruction_pointer, 2);
+}
+
+#[test]
+fn test_random() {
+    let mut source = std::fs::read_to_string("tests/test22.pdra").expect("Unable to read file");
+    source = normalize(&source);
+    let parser = ProgramParser::new();
+    let mut manager = ProgramManager::new();
+    let result = parser.parse(&mut manager, Lexer::new(&source));
+    assert!(result.is_ok());
+    assert!(manager.diagnostics.is_empty());
+    let program = CompiledProgram::from_program(&manager);
+    let value = |values: &[(String, Value)], name: &str| {
+        values
+            .iter()
+            .find(|(var, _)| var == name)
+            .unwrap()
+            .1
+            .clone()
+    };
+
+    // The same seed draws the same numbers, even on another machine
+    let mut machine = VirtualMachine::new(&program);
+    machine.set_seed(7);
+    machine.run().unwrap();
+    let expected = main_values(&program, &machine);
+    assert_eq!(value(&expected, "outside"), Value::Int(0));
+    assert_eq!(value(&expected, "fixed"), Value::Int(3));
+    machine.run().unwrap();
+    assert_eq!(main_values(&program, &machine), expected);
+    let mut other = VirtualMachine::new(&program);
+    other.set_seed(7);
+    other.run().unwrap();
+    assert_eq!(main_values(&program, &other), expected);
+    other.set_seed(8);
+    other.run().unwrap();
+    assert_ne!(main_values(&program, &other), expected);
+
+    // Snapshots keep the state of the random numbers, whatever the seed of
+    // the machine that resumes them
+    machine.reset();
+    for _ in 0..200 {
+        machine.step().unwrap();
+    }
+    let snapshot = Snapshot::from_text(&machine.snapshot().to_text()).unwrap();
+    assert_eq!(snapshot, machine.snapshot());
+    other.restore(&snapshot).unwrap();
+    other.resume().unwrap();
+    assert_eq!(main_values(&program, &other), expected);
+
+    // Undoing a call takes its number back too
+    machine.record_history(true);
+    machine.run().unwrap();
+    let total = machine.executed_quads();
+    machine.rewind(total / 2).unwrap();
+    machine.resume().unwrap();
+    assert_eq!(main_values(&program, &machine), expected);
+
+    let program = assemble(
+        "    GOTO main\nfunction main\n    temp t1 int\nmain:\n    PARAM 5\n    PARAM 1\n    CALLBUILTIN rand_int:int:int -> t1\n    ENDPROGRAM\nend\n",
+    )
+    .unwrap();
+    let error = VirtualMachine::new(&program).run().unwrap_err();
+    assert_eq!(error.message, "rand_int of an empty range: 5 to 1");
+    assert_eq!(error.instruction_pointer, 3);
+
+    // A call whose result can't be written leaves the random numbers as they
+    // were, so resuming draws the same one
+    let mut program = assemble(
+        "    GOTO main\nfunction main\n    var x float\n    var n int\nmain:\n    CALLBUILTIN rand_float -> x\n    ENDPROGRAM\nend\n",
+    )
+    .unwrap();
+    let n = program.functions["main"].vars["n"].address;
+    let call = program.quadruplets.get(1).unwrap().clone();
+    let quad = Quadruplet::new(call.operator, call.arg1, call.arg2, Some(n));
+    program.quadruplets.set(1, quad);
+    let mut machine = VirtualMachine::new(&program);
+    machine.set_seed(7);
+    machine.reset();
+    machine.step().unwrap();
+    let before = machine.snapshot();
+    let error = machine.resume().unwrap_err();
+    assert_eq!(error.instruction_pointer, 1);
+    assert_eq!(machine.snapshot(), before);
+}
+
+// Compiles a fixture through the library entry point, with its whitespace
+// collapsed like the tests above
+fn compile_file(path: &str, options: &CompileOptions) -> Result<CompiledProgram, Vec<Diagnostic>> {
+    let source = std::fs::read_to_string(path).expect("Unable to read file");
+    compile(&normalize(&source), options)
+}
+
+#[test]
+fn test_compile_and_run() {
+    let diagnostics = compile_file("tests/test2.pdra", &CompileOptions::default()).unwrap_err();
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic::new(
+            "Unexpected While, expected one of \"main\", \"var\", \"const\", \"void\"".to_string(),
+            Some(16)
+        )]
+    );
+
+    let program = compile_file("tests/test8.pdra", &CompileOptions::default()).unwrap();
+    let mut output = vec![];
+    let outcome = run(&program, &mut output, Limits::default()).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Escalado: "));
+    assert!(output.ends_with("System exited with a value 0\n"));
+    assert!(outcome.executed_quads > 0);
+    assert_eq!(outcome.globals, global_values(&program));
+
+    let limits = Limits {
+        max_quads: Some(5),
+        ..Limits::default()
+    };
+    let error = run(&program, &mut std::io::sink(), limits).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::QuadLimit);
+
+    // Optimizing doesn't change what the program computes
+    let source = normalize(&std::fs::read_to_string("tests/test1.pdra").unwrap());
+    let options = CompileOptions {
+        optimize: true,
+        ..CompileOptions::default()
+    };
+    let (optimized, reports) = compile_with_reports(&source, &options).unwrap();
+    assert!(reports
+        .iter()
+        .any(|report| report.size_after() < report.size_before()));
+    let program = compile(&source, &CompileOptions::default()).unwrap();
+    assert_eq!(global_values(&optimized), global_values(&program));
+}
+
+#[test]
+fn test_compile_host_functions() {
+    let mut options = CompileOptions::default();
+    options
+        .builtins
+        .register_host("get_setting", vec![Type::Int], Type::Float, |_| {
+            Ok(Value::Float(2.0))
+        })
+        .unwrap();
+    options
+        .builtins
+        .register_host("log", vec![Type::Float], Type::Int, |_| Ok(Value::Int(7)))
+        .unwrap();
+    let diagnostics = compile_file("tests/test21.pdra", &CompileOptions::default()).unwrap_err();
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.message == "There's no builtin function get_setting"));
+
+    let program = compile_file("tests/test21.pdra", &options).unwrap();
+    let outcome = run(&program, &mut std::io::sink(), Limits::default()).unwrap();
+    assert!(outcome
+        .globals
+        .contains(&("logged".to_string(), Value::Int(7))));
+}
+
+fn semantic_errors(body: &str) -> Vec<String> {
+    let source = format!("program errors; var a: int; b: float; {} end", body);
+    compile(&source, &CompileOptions::default())
+        .unwrap_err()
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[test]
+fn test_undeclared_variables() {
+    assert_eq!(
+        semantic_errors("main { a = c + 1; }"),
+        vec!["There's no variable c".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("main { c = a + 1; }"),
+        vec!["There's no variable c".to_string()]
+    );
+}
+
+#[test]
+fn test_assign_type_errors() {
+    assert_eq!(
+        semantic_errors("main { a = b; }"),
+        vec!["Types must be the same to assign values".to_string()]
+    );
+}
+
+#[test]
+fn test_operand_type_errors() {
+    assert_eq!(
+        semantic_errors("main { a = (1 < 2) + 1; }"),
+        vec!["Operator + can't be applied to bool and int".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("main { if ((1 < 2) != (2 < 3)) { a = 1; } }"),
+        vec!["Operator != can't be applied to bool and bool".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("main { a = 1 + (2 < 3); }"),
+        vec!["Operator + can't be applied to int and bool".to_string()]
+    );
+}
+
+#[test]
+fn test_condition_type_errors() {
+    assert_eq!(
+        semantic_errors("main { while (a + 1) do { a = a - 1; } }"),
+        vec!["Invalid type for while condition".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("main { if (a) { a = 1; } }"),
+        vec!["Invalid type for if condition".to_string()]
+    );
+}
+
+#[test]
+fn test_void_function_values() {
+    assert_eq!(
+        semantic_errors("void f(n: int) [ { a = n; } ]; main { a = f(1) + 1; }"),
+        vec!["Function f doesn't return a value and can't be used in an expression".to_string()]
+    );
+}
+
+#[test]
+fn test_redeclarations() {
+    assert_eq!(
+        semantic_errors("void f() [ { a = 1; } ]; void f() [ { a = 2; } ]; main { f(); }"),
+        vec!["Function Id: f already registered".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("void f(n: int, n: float) [ { a = n; } ]; main { f(1, 2.0); }"),
+        vec!["Variable n is already declared in the params of the function f".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("void f(n: int) [ var n: int; { a = n; } ]; main { f(1); }"),
+        vec!["Variable n is already declared either in the params or in the function".to_string()]
+    );
+    assert_eq!(
+        semantic_errors("a: int; main { a = 1; }"),
+        vec!["Global variable a is already declared".to_string()]
+    );
+}